@@ -0,0 +1,601 @@
+use super::{
+    CombatStats, Door, EntryTrigger, Hidden, HungerClock, Item, Map, Monster, Name, Position,
+    RunState, TileType, gamelog::GameLog, player::try_move_player,
+    regeneration_system::can_regenerate,
+};
+use rltk::{BaseMap, Point};
+use specs::prelude::*;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
+
+/// Something the player keeps doing, a turn at a time, until it's finished or
+/// something interrupts them.
+#[derive(PartialEq, Copy, Clone)]
+pub enum AutoAction {
+    /// Walk towards the nearest part of the level that hasn't been seen yet.
+    Explore,
+    /// Walk to the tile at map index `destination`.
+    Travel { destination: usize },
+    /// Wait until fully healed.
+    Rest,
+}
+
+/// The player's current automatic action, if any, and what they knew when they last
+/// took a step so that anything new can interrupt them.
+#[derive(Default)]
+pub struct Autopilot {
+    action: Option<AutoAction>,
+    hp: i32,
+    seen_items: Vec<Entity>,
+    /// The tiles still to walk through on the current trip, next first.
+    route: VecDeque<usize>,
+    /// Whether `route` steers clear of the traps the player knows about.
+    route_avoids_traps: bool,
+}
+
+/// Starts the player on `action`, unless there's a monster in view.
+pub fn start(ecs: &mut World, action: AutoAction) {
+    if let Some(monster) = visible_monsters(ecs).first() {
+        ecs.fetch_mut::<GameLog>()
+            .add(format!("You can't do that with the {} in view.", monster));
+        return;
+    }
+
+    let hp = player_hp(ecs);
+    if action == AutoAction::Rest
+        && let Some(reason) = why_not_rest(ecs)
+    {
+        ecs.fetch_mut::<GameLog>().add(reason);
+        return;
+    }
+
+    let seen_items = visible_items(ecs);
+    let mut autopilot = ecs.fetch_mut::<Autopilot>();
+    autopilot.action = Some(action);
+    autopilot.hp = hp;
+    autopilot.seen_items = seen_items;
+    autopilot.route.clear();
+}
+
+pub fn is_active(ecs: &World) -> bool {
+    ecs.fetch::<Autopilot>().action.is_some()
+}
+
+pub fn stop(ecs: &mut World) {
+    let mut autopilot = ecs.fetch_mut::<Autopilot>();
+    autopilot.action = None;
+    autopilot.route.clear();
+}
+
+/// Takes the next step of the player's automatic action, or stops it if they've been
+/// interrupted or there's nothing left to do.
+pub fn step(ecs: &mut World) -> RunState {
+    let Some(action) = ecs.fetch::<Autopilot>().action else {
+        return RunState::AwaitingInput;
+    };
+
+    if let Some(reason) = interruption(ecs) {
+        ecs.fetch_mut::<GameLog>().add(reason);
+        stop(ecs);
+        return RunState::AwaitingInput;
+    }
+
+    let next_step = match action {
+        AutoAction::Explore => {
+            let next_step = next_explore_step(ecs);
+            if next_step.is_none() {
+                ecs.fetch_mut::<GameLog>()
+                    .add("There is nowhere left to explore.");
+            }
+            next_step
+        }
+        AutoAction::Travel { destination } => next_travel_step(ecs, destination),
+        AutoAction::Rest => {
+            if let Some(reason) = why_not_rest(ecs) {
+                ecs.fetch_mut::<GameLog>().add(reason);
+                stop(ecs);
+                return RunState::AwaitingInput;
+            }
+            return RunState::PlayerTurn;
+        }
+    };
+    let Some((delta_x, delta_y)) = next_step else {
+        stop(ecs);
+        return RunState::AwaitingInput;
+    };
+
+    try_move_player(delta_x, delta_y, ecs)
+}
+
+/// Why the player should stop what they're doing: a monster has come into view, an
+/// item has been spotted, or they've been hurt.
+fn interruption(ecs: &mut World) -> Option<String> {
+    if let Some(monster) = visible_monsters(ecs).first() {
+        return Some(format!("You see a {} and stop.", monster));
+    }
+
+    let hp = player_hp(ecs);
+    let items = visible_items(ecs);
+    let mut autopilot = ecs.fetch_mut::<Autopilot>();
+    if hp < autopilot.hp {
+        return Some(String::from("You stop."));
+    }
+    autopilot.hp = hp;
+
+    let new_item = items
+        .iter()
+        .find(|item| !autopilot.seen_items.contains(item))
+        .copied();
+    autopilot.seen_items = items;
+    new_item.map(|item| {
+        let names = ecs.read_storage::<Name>();
+        let name = names
+            .get(item)
+            .map_or("something", |name| name.name.as_str());
+        format!("You spot the {} and stop.", name)
+    })
+}
+
+/// Why resting any longer wouldn't do the player any good, if it wouldn't.
+fn why_not_rest(ecs: &World) -> Option<&'static str> {
+    let player_entity = ecs.fetch::<Entity>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let hunger_clocks = ecs.read_storage::<HungerClock>();
+
+    let stats = combat_stats.get(*player_entity)?;
+    if stats.hp >= stats.max_hp {
+        Some("You feel rested.")
+    } else if !can_regenerate(hunger_clocks.get(*player_entity)) {
+        Some("You are too hungry to rest.")
+    } else {
+        None
+    }
+}
+
+/// Whether the player is willing to walk over tile `idx` automatically: it has been
+/// seen and is safe. Closed doors open when walked into, but anything else in the
+/// way is avoided, as are traps the player knows about if `avoid_traps` is set.
+fn is_safe_tile(
+    map: &Map,
+    doors: &ReadStorage<Door>,
+    traps: &ReadStorage<EntryTrigger>,
+    hidden: &ReadStorage<Hidden>,
+    idx: usize,
+    avoid_traps: bool,
+) -> bool {
+    let door_here = map.tile_content[idx]
+        .iter()
+        .any(|entity| doors.contains(*entity));
+    let known_trap_here = map.tile_content[idx]
+        .iter()
+        .any(|entity| traps.contains(*entity) && !hidden.contains(*entity));
+    map.revealed_tiles[idx]
+        && map.tiles[idx].is_walkable()
+        && map.tiles[idx].damage_per_turn() == 0
+        && !(avoid_traps && known_trap_here)
+        && (!map.blocked[idx] || door_here)
+}
+
+/// A copy of the map where only the tiles that pass `is_safe_tile` are open.
+fn autopilot_map(ecs: &World, avoid_traps: bool) -> Map {
+    let map = ecs.fetch::<Map>();
+    let doors = ecs.read_storage::<Door>();
+    let traps = ecs.read_storage::<EntryTrigger>();
+    let hidden = ecs.read_storage::<Hidden>();
+
+    let mut autopilot_map = (*map).clone();
+    for idx in 0..map.tiles.len() {
+        autopilot_map.blocked[idx] = !is_safe_tile(&map, &doors, &traps, &hidden, idx, avoid_traps);
+    }
+    autopilot_map
+}
+
+/// The direction of the next step along the planned route, planning a new one with
+/// `plan` if there isn't one or the next step is no longer safe. Routes steer clear
+/// of known traps if there's any other way. `None` if there's nowhere to go.
+fn follow_route(
+    ecs: &mut World,
+    plan: impl Fn(&World, bool) -> Option<VecDeque<usize>>,
+) -> Option<(i32, i32)> {
+    if !route_is_usable(ecs) {
+        let (route, avoids_traps) = match plan(ecs, true) {
+            Some(route) => (route, true),
+            None => (plan(ecs, false)?, false),
+        };
+        let mut autopilot = ecs.fetch_mut::<Autopilot>();
+        autopilot.route = route;
+        autopilot.route_avoids_traps = avoids_traps;
+    }
+
+    let next_idx = ecs.fetch_mut::<Autopilot>().route.pop_front()?;
+    let player_pos = *ecs.fetch::<Point>();
+    let width = ecs.fetch::<Map>().width;
+    let next_x = next_idx as i32 % width;
+    let next_y = next_idx as i32 / width;
+    Some((next_x - player_pos.x, next_y - player_pos.y))
+}
+
+/// Whether the next step of the planned route is still one step away and safe to
+/// take. The player can be moved off their route, say by a trap, and they may have
+/// spotted a trap on it since it was planned.
+fn route_is_usable(ecs: &World) -> bool {
+    let autopilot = ecs.fetch::<Autopilot>();
+    let Some(next_idx) = autopilot.route.front() else {
+        return false;
+    };
+
+    let map = ecs.fetch::<Map>();
+    let player_pos = *ecs.fetch::<Point>();
+    let next_x = *next_idx as i32 % map.width;
+    let next_y = *next_idx as i32 / map.width;
+    if (next_x - player_pos.x).abs() > 1 || (next_y - player_pos.y).abs() > 1 {
+        return false;
+    }
+
+    is_safe_tile(
+        &map,
+        &ecs.read_storage::<Door>(),
+        &ecs.read_storage::<EntryTrigger>(),
+        &ecs.read_storage::<Hidden>(),
+        *next_idx,
+        autopilot.route_avoids_traps,
+    )
+}
+
+/// The way to the nearest tile the player hasn't seen yet, found by walking
+/// downhill on a Dijkstra map seeded with every such tile.
+fn plan_explore_route(ecs: &World, avoid_traps: bool) -> Option<VecDeque<usize>> {
+    let walk_map = autopilot_map(ecs, avoid_traps);
+    let unexplored: Vec<usize> = (0..walk_map.tiles.len())
+        .filter(|idx| !walk_map.revealed_tiles[*idx])
+        .collect();
+    let player_pos = *ecs.fetch::<Point>();
+
+    let mut dijkstra_map = rltk::DijkstraMap::new(
+        walk_map.width as usize,
+        walk_map.height as usize,
+        &unexplored,
+        &walk_map,
+        f32::MAX,
+    );
+    // With only a few targets, rltk doesn't mark the targets themselves as 0 away
+    for idx in unexplored.iter() {
+        dijkstra_map.map[*idx] = 0.0;
+    }
+
+    let mut route = VecDeque::new();
+    let mut idx = walk_map.xy_idx(player_pos.x, player_pos.y);
+    loop {
+        let downhill = walk_map
+            .get_available_exits(idx)
+            .iter()
+            .map(|(exit, _cost)| *exit)
+            .filter(|exit| dijkstra_map.map[*exit] < dijkstra_map.map[idx])
+            .min_by(|a, b| dijkstra_map.map[*a].total_cmp(&dijkstra_map.map[*b]));
+        match downhill {
+            Some(next_idx) => {
+                idx = next_idx;
+                route.push_back(idx);
+            }
+            None => break,
+        }
+    }
+
+    // Unseen tiles are blocked, so the walk stops next to one; finish on it
+    let x = idx as i32 % walk_map.width;
+    let y = idx as i32 / walk_map.width;
+    let target = unexplored.iter().copied().find(|target| {
+        let target_x = *target as i32 % walk_map.width;
+        let target_y = *target as i32 / walk_map.width;
+        (target_x - x).abs() <= 1 && (target_y - y).abs() <= 1
+    })?;
+    route.push_back(target);
+    Some(route)
+}
+
+/// The cheapest way to `destination`.
+fn plan_travel_route(
+    ecs: &World,
+    destination: usize,
+    avoid_traps: bool,
+) -> Option<VecDeque<usize>> {
+    let walk_map = autopilot_map(ecs, avoid_traps);
+    let player_pos = *ecs.fetch::<Point>();
+    a_star(
+        &walk_map,
+        walk_map.xy_idx(player_pos.x, player_pos.y),
+        destination,
+    )
+}
+
+/// A tile waiting to be looked at by `a_star`, ordered so that the one with the
+/// lowest estimated total cost comes out of the heap first.
+struct Frontier {
+    estimate: f32,
+    idx: usize,
+}
+
+impl PartialEq for Frontier {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimate == other.estimate
+    }
+}
+
+impl Eq for Frontier {}
+
+impl PartialOrd for Frontier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Frontier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+/// The cheapest route from `start` to `end`, not counting `start` itself. rltk's own
+/// `a_star_search` doesn't add up the cost of the path so far, so it can't be
+/// relied on to find the cheapest route, or sometimes any route at all.
+fn a_star(map: &Map, start: usize, end: usize) -> Option<VecDeque<usize>> {
+    let mut cost_so_far = vec![f32::MAX; map.tiles.len()];
+    let mut came_from: Vec<Option<usize>> = vec![None; map.tiles.len()];
+    let mut frontier = BinaryHeap::new();
+
+    cost_so_far[start] = 0.0;
+    frontier.push(Frontier {
+        estimate: map.get_pathing_distance(start, end),
+        idx: start,
+    });
+
+    while let Some(Frontier { estimate, idx }) = frontier.pop() {
+        if idx == end {
+            let mut route = VecDeque::new();
+            let mut step = end;
+            while step != start {
+                route.push_front(step);
+                step = came_from[step]?;
+            }
+            return Some(route);
+        }
+        // Skip tiles that were queued again after a cheaper way to them was found
+        if estimate > cost_so_far[idx] + map.get_pathing_distance(idx, end) {
+            continue;
+        }
+
+        for (exit, cost) in map.get_available_exits(idx) {
+            let cost = cost_so_far[idx] + cost;
+            if cost < cost_so_far[exit] {
+                cost_so_far[exit] = cost;
+                came_from[exit] = Some(idx);
+                frontier.push(Frontier {
+                    estimate: cost + map.get_pathing_distance(exit, end),
+                    idx: exit,
+                });
+            }
+        }
+    }
+    None
+}
+
+/// The direction of the next step towards the nearest tile the player hasn't seen
+/// yet. The route there is only planned again once that tile has been seen.
+fn next_explore_step(ecs: &mut World) -> Option<(i32, i32)> {
+    let target_seen = {
+        let map = ecs.fetch::<Map>();
+        ecs.fetch::<Autopilot>()
+            .route
+            .back()
+            .is_some_and(|idx| map.revealed_tiles[*idx])
+    };
+    if target_seen {
+        ecs.fetch_mut::<Autopilot>().route.clear();
+    }
+    follow_route(ecs, plan_explore_route)
+}
+
+/// The direction of the next step on the way to `destination`, or `None` once the
+/// player is there or if there's no way to get there.
+fn next_travel_step(ecs: &mut World, destination: usize) -> Option<(i32, i32)> {
+    let player_pos = *ecs.fetch::<Point>();
+    if ecs.fetch::<Map>().xy_idx(player_pos.x, player_pos.y) == destination {
+        return None;
+    }
+
+    let next_step = follow_route(ecs, |ecs, avoid_traps| {
+        plan_travel_route(ecs, destination, avoid_traps)
+    });
+    if next_step.is_none() {
+        ecs.fetch_mut::<GameLog>()
+            .add("You don't know a way to get there.");
+    }
+    next_step
+}
+
+/// Where the player has seen a way down on this level, if they have.
+pub fn known_down_stairs(ecs: &World) -> Option<usize> {
+    let map = ecs.fetch::<Map>();
+    (0..map.tiles.len())
+        .find(|idx| map.revealed_tiles[*idx] && map.tiles[*idx] == TileType::DownStairs)
+}
+
+/// The names of the monsters the player can see.
+fn visible_monsters(ecs: &World) -> Vec<String> {
+    let map = ecs.fetch::<Map>();
+    let monsters = ecs.read_storage::<Monster>();
+    let positions = ecs.read_storage::<Position>();
+    let names = ecs.read_storage::<Name>();
+
+    (&monsters, &positions, &names)
+        .join()
+        .filter(|(_monster, pos, _name)| map.visible_tiles[map.xy_idx(pos.x, pos.y)])
+        .map(|(_monster, _pos, name)| name.name.clone())
+        .collect()
+}
+
+/// The items lying on the floor where the player can see them.
+fn visible_items(ecs: &World) -> Vec<Entity> {
+    let map = ecs.fetch::<Map>();
+    let entities = ecs.entities();
+    let items = ecs.read_storage::<Item>();
+    let positions = ecs.read_storage::<Position>();
+
+    (&entities, &items, &positions)
+        .join()
+        .filter(|(_entity, _item, pos)| map.visible_tiles[map.xy_idx(pos.x, pos.y)])
+        .map(|(entity, _item, _pos)| entity)
+        .collect()
+}
+
+fn player_hp(ecs: &World) -> i32 {
+    let player_entity = ecs.fetch::<Entity>();
+    ecs.read_storage::<CombatStats>()
+        .get(*player_entity)
+        .map_or(0, |stats| stats.hp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    /// A walled room of open floor.
+    fn open_map(width: i32, height: i32) -> Map {
+        let mut map = Map::new(width, height);
+        for y in 1..height - 1 {
+            for x in 1..width - 1 {
+                let idx = map.xy_idx(x, y);
+                map.tiles[idx] = TileType::Floor;
+            }
+        }
+        map.populate_blocked();
+        map
+    }
+
+    fn set_tiles(map: &mut Map, x: i32, ys: std::ops::RangeInclusive<i32>, tile: TileType) {
+        for y in ys {
+            let idx = map.xy_idx(x, y);
+            map.tiles[idx] = tile;
+        }
+        map.populate_blocked();
+    }
+
+    fn assert_connected(map: &Map, start: usize, route: &VecDeque<usize>) {
+        let mut from = start;
+        for idx in route.iter() {
+            let (from_x, from_y) = (from as i32 % map.width, from as i32 / map.width);
+            let (x, y) = (*idx as i32 % map.width, *idx as i32 / map.width);
+            assert!((x - from_x).abs() <= 1 && (y - from_y).abs() <= 1);
+            assert!(map.tiles[*idx].is_walkable());
+            from = *idx;
+        }
+    }
+
+    #[test]
+    fn a_star_walks_straight_across_open_floor() {
+        let map = open_map(10, 5);
+        let (start, end) = (map.xy_idx(1, 2), map.xy_idx(8, 2));
+        let route = a_star(&map, start, end).unwrap();
+        assert_eq!(route.len(), 7);
+        assert_eq!(route.back(), Some(&end));
+        assert_connected(&map, start, &route);
+    }
+
+    #[test]
+    fn a_star_goes_through_the_gap_in_a_wall() {
+        let mut map = open_map(11, 7);
+        set_tiles(&mut map, 5, 1..=4, TileType::Wall);
+        let (start, end) = (map.xy_idx(1, 1), map.xy_idx(9, 1));
+        let route = a_star(&map, start, end).unwrap();
+        assert!(route.contains(&map.xy_idx(5, 5)));
+        assert_eq!(route.back(), Some(&end));
+        assert_connected(&map, start, &route);
+    }
+
+    #[test]
+    fn a_star_walks_around_lava_when_it_is_cheaper() {
+        let mut map = open_map(11, 7);
+        set_tiles(&mut map, 5, 1..=4, TileType::Lava);
+        let (start, end) = (map.xy_idx(1, 2), map.xy_idx(9, 2));
+        let route = a_star(&map, start, end).unwrap();
+        assert!(route.iter().all(|idx| map.tiles[*idx] != TileType::Lava));
+        assert_connected(&map, start, &route);
+    }
+
+    #[test]
+    fn a_star_gives_up_when_there_is_no_way_through() {
+        let mut map = open_map(11, 7);
+        set_tiles(&mut map, 5, 1..=5, TileType::Wall);
+        assert!(a_star(&map, map.xy_idx(1, 1), map.xy_idx(9, 1)).is_none());
+    }
+
+    /// A world with just what the autopilot needs, where the player has seen all of
+    /// `map`.
+    fn autopilot_world(mut map: Map, player: Point) -> World {
+        map.revealed_tiles.fill(true);
+        let mut ecs = World::new();
+        ecs.register::<Door>();
+        ecs.register::<EntryTrigger>();
+        ecs.register::<Hidden>();
+        ecs.insert(map);
+        ecs.insert(player);
+        ecs.insert(Autopilot::default());
+        ecs
+    }
+
+    /// Moves the player the way `follow_route` told them to.
+    fn take_step(ecs: &mut World, (delta_x, delta_y): (i32, i32)) {
+        let mut player_pos = ecs.fetch_mut::<Point>();
+        player_pos.x += delta_x;
+        player_pos.y += delta_y;
+    }
+
+    #[test]
+    fn routes_are_planned_once_and_then_followed() {
+        let mut ecs = autopilot_world(open_map(10, 5), Point::new(1, 2));
+        let plans = Cell::new(0);
+        let plan = |ecs: &World, _avoid_traps: bool| {
+            plans.set(plans.get() + 1);
+            let map = ecs.fetch::<Map>();
+            let player_pos = ecs.fetch::<Point>();
+            a_star(
+                &map,
+                map.xy_idx(player_pos.x, player_pos.y),
+                map.xy_idx(8, 2),
+            )
+        };
+
+        for _step in 0..7 {
+            let step = follow_route(&mut ecs, plan).unwrap();
+            assert_eq!(step, (1, 0));
+            take_step(&mut ecs, step);
+        }
+        assert_eq!(plans.get(), 1);
+        assert_eq!(*ecs.fetch::<Point>(), Point::new(8, 2));
+    }
+
+    #[test]
+    fn routes_are_planned_again_when_the_player_is_moved_off_them() {
+        let mut ecs = autopilot_world(open_map(10, 5), Point::new(1, 2));
+        let plans = Cell::new(0);
+        let plan = |ecs: &World, _avoid_traps: bool| {
+            plans.set(plans.get() + 1);
+            let map = ecs.fetch::<Map>();
+            let player_pos = ecs.fetch::<Point>();
+            a_star(
+                &map,
+                map.xy_idx(player_pos.x, player_pos.y),
+                map.xy_idx(8, 2),
+            )
+        };
+
+        let step = follow_route(&mut ecs, plan).unwrap();
+        take_step(&mut ecs, step);
+        *ecs.fetch_mut::<Point>() = Point::new(5, 3);
+        let (delta_x, delta_y) = follow_route(&mut ecs, plan).unwrap();
+        assert_eq!(plans.get(), 2);
+        assert_eq!(delta_x, 1);
+        assert!(delta_y == 0 || delta_y == -1);
+    }
+}
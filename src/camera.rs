@@ -0,0 +1,129 @@
+use super::{
+    Hidden, Map, Monster, Position, Renderable, Telepathy, TileType, map_builders::MapSnapshot,
+};
+use rltk::{Point, RGB, Rltk};
+use specs::prelude::*;
+
+/// The part of the screen the map is drawn into; the UI panel sits below it.
+pub const VIEW_WIDTH: i32 = 80;
+pub const VIEW_HEIGHT: i32 = 43;
+
+/// The map coordinates shown in the top-left corner of the screen, keeping the
+/// player in the middle of the view.
+pub fn get_screen_bounds(ecs: &World) -> Point {
+    let player_pos = ecs.fetch::<Point>();
+    Point::new(
+        player_pos.x - VIEW_WIDTH / 2,
+        player_pos.y - VIEW_HEIGHT / 2,
+    )
+}
+
+/// Translates a screen position, such as the mouse cursor, into map coordinates.
+/// Returns `None` when the position isn't over the map view.
+pub fn screen_to_world(ecs: &World, screen: Point) -> Option<Point> {
+    if screen.x < 0 || screen.x >= VIEW_WIDTH || screen.y < 0 || screen.y >= VIEW_HEIGHT {
+        return None;
+    }
+    let origin = get_screen_bounds(ecs);
+    Some(Point::new(screen.x + origin.x, screen.y + origin.y))
+}
+
+/// Draws the part of the map around the player, and the visible entities on it. A
+/// telepathic player also sees every monster, wherever it is.
+pub fn render_camera(ecs: &World, ctx: &mut Rltk) {
+    let map = ecs.fetch::<Map>();
+    let origin = get_screen_bounds(ecs);
+    render_tiles(&map, origin, ctx);
+
+    let positions = ecs.read_storage::<Position>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let hidden = ecs.read_storage::<Hidden>();
+    let monsters = ecs.read_storage::<Monster>();
+    let player_entity = ecs.fetch::<Entity>();
+    let telepathic = ecs.read_storage::<Telepathy>().contains(*player_entity);
+
+    // Draw the highest render order first, so whatever has the lowest ends up on top
+    let mut drawables: Vec<(&Position, &Renderable)> =
+        (&positions, &renderables, !&hidden, monsters.maybe())
+            .join()
+            .filter(|(pos, _render, _hidden, monster)| {
+                map.visible_tiles[map.xy_idx(pos.x, pos.y)] || (telepathic && monster.is_some())
+            })
+            .map(|(pos, render, _hidden, _monster)| (pos, render))
+            .collect();
+    drawables.sort_by_key(|(_pos, render)| std::cmp::Reverse(render.render_order));
+
+    for (pos, render) in drawables {
+        let screen_x = pos.x - origin.x;
+        let screen_y = pos.y - origin.y;
+        if (0..VIEW_WIDTH).contains(&screen_x) && (0..VIEW_HEIGHT).contains(&screen_y) {
+            ctx.set(screen_x, screen_y, render.fg, render.bg, render.glyph);
+        }
+    }
+}
+
+/// Draws a map snapshot for the map generation visualizer. The whole map is shown,
+/// centered on the screen; maps too big for the view are shrunk by drawing only
+/// every second (or third, ...) tile in each direction.
+pub fn render_debug_map(snapshot: &MapSnapshot, ctx: &mut Rltk) {
+    let scale = i32::max(
+        (snapshot.width + VIEW_WIDTH - 1) / VIEW_WIDTH,
+        (snapshot.height + VIEW_HEIGHT - 1) / VIEW_HEIGHT,
+    )
+    .max(1);
+    let shown_width = (snapshot.width + scale - 1) / scale;
+    let shown_height = (snapshot.height + scale - 1) / scale;
+    let left = (VIEW_WIDTH - shown_width) / 2;
+    let top = (VIEW_HEIGHT - shown_height) / 2;
+
+    for shown_y in 0..shown_height {
+        for shown_x in 0..shown_width {
+            let idx = (shown_y * scale * snapshot.width + shown_x * scale) as usize;
+            let (glyph, fg) = tile_glyph(snapshot.tiles[idx]);
+            ctx.set(
+                left + shown_x,
+                top + shown_y,
+                fg,
+                RGB::from_f32(0., 0., 0.),
+                glyph,
+            );
+        }
+    }
+}
+
+fn render_tiles(map: &Map, origin: Point, ctx: &mut Rltk) {
+    for screen_y in 0..VIEW_HEIGHT {
+        for screen_x in 0..VIEW_WIDTH {
+            let x = screen_x + origin.x;
+            let y = screen_y + origin.y;
+            if x < 0 || x >= map.width || y < 0 || y >= map.height {
+                continue;
+            }
+
+            let idx = map.xy_idx(x, y);
+            if !map.revealed_tiles[idx] {
+                continue;
+            }
+
+            let (glyph, mut fg) = tile_glyph(map.tiles[idx]);
+            if !map.visible_tiles[idx] {
+                fg = fg.to_greyscale()
+            }
+            ctx.set(screen_x, screen_y, fg, RGB::from_f32(0., 0., 0.), glyph);
+        }
+    }
+}
+
+fn tile_glyph(tile: TileType) -> (rltk::FontCharType, RGB) {
+    match tile {
+        TileType::Floor => (rltk::to_cp437('.'), RGB::from_f32(0.0, 0.5, 0.5)),
+        TileType::Wall => (rltk::to_cp437('#'), RGB::from_f32(0., 1.0, 0.)),
+        TileType::DownStairs => (rltk::to_cp437('>'), RGB::from_f32(0., 1.0, 1.0)),
+        TileType::ShallowWater => (rltk::to_cp437('~'), RGB::from_f32(0.3, 0.6, 1.0)),
+        TileType::DeepWater => (rltk::to_cp437('≈'), RGB::from_f32(0.0, 0.2, 0.9)),
+        TileType::Lava => (rltk::to_cp437('~'), RGB::from_f32(1.0, 0.35, 0.0)),
+        TileType::Chasm => (rltk::to_cp437('░'), RGB::from_f32(0.25, 0.25, 0.25)),
+        TileType::Bridge => (rltk::to_cp437('='), RGB::from_f32(0.6, 0.4, 0.2)),
+        TileType::Grass => (rltk::to_cp437('"'), RGB::from_f32(0.2, 0.6, 0.2)),
+    }
+}
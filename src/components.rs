@@ -2,7 +2,7 @@ use rltk::RGB;
 use specs::prelude::*;
 use specs_derive::*;
 
-#[derive(Component)]
+#[derive(Component, Clone)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
@@ -13,6 +13,8 @@ pub struct Renderable {
     pub glyph: rltk::FontCharType,
     pub fg: RGB,
     pub bg: RGB,
+    /// Which entity is drawn when several share a tile: the lowest order ends up on top.
+    pub render_order: i32,
 }
 
 #[derive(Component, Debug)]
@@ -26,6 +28,12 @@ pub struct Name {
     pub name: String,
 }
 
+/// A sentence or two about an entity, shown when the player looks at it.
+#[derive(Component, Debug)]
+pub struct Description {
+    pub text: String,
+}
+
 #[derive(Component)]
 pub struct Viewshed {
     pub visible_tiles: Vec<rltk::Point>,
@@ -36,6 +44,14 @@ pub struct Viewshed {
 #[derive(Component, Debug)]
 pub struct BlocksTile {}
 
+#[derive(Component, Debug)]
+pub struct BlocksVisibility {}
+
+#[derive(Component, Debug)]
+pub struct Door {
+    pub open: bool,
+}
+
 #[derive(Component, Debug)]
 pub struct CombatStats {
     pub max_hp: i32,
@@ -81,8 +97,97 @@ pub struct InBackpack {
     pub owner: Entity,
 }
 
+/// An item the player has picked from their inventory to use this turn.
+#[derive(Component, Debug, Clone)]
+pub struct WantsToUseItem {
+    pub item: Entity,
+}
+
 #[derive(Component, Debug, Clone)]
 pub struct WantsToPickUpItem {
     pub collected_by: Entity,
     pub item: Entity,
 }
+
+#[derive(Component, Debug)]
+pub struct Hidden {}
+
+#[derive(Component, Debug)]
+pub struct EntryTrigger {}
+
+#[derive(Component, Debug)]
+pub struct SingleActivation {}
+
+#[derive(Component, Debug)]
+pub struct EntityMoved {}
+
+#[derive(Component, Debug)]
+pub struct InflictsDamage {
+    pub damage: i32,
+}
+
+#[derive(Component, Debug)]
+pub struct TeleportsTarget {}
+
+/// Someone the player can trade with by walking into them.
+#[derive(Component, Debug)]
+pub struct Vendor {}
+
+/// Gold carried by an entity.
+#[derive(Component, Debug)]
+pub struct Wallet {
+    pub gold: i32,
+}
+
+/// What an item is worth in gold when bought from a vendor.
+#[derive(Component, Debug)]
+pub struct ItemValue {
+    pub value: i32,
+}
+
+/// Coins lying on the floor, added to the wallet of whoever picks them up.
+#[derive(Component, Debug)]
+pub struct GoldPile {
+    pub amount: i32,
+}
+
+/// What's left of something that died. Rots away once `turns_left` runs out.
+#[derive(Component, Debug)]
+pub struct Corpse {
+    pub turns_left: i32,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum HungerState {
+    WellFed,
+    Normal,
+    Hungry,
+    Starving,
+}
+
+/// How hungry an entity is, and how many turns until it gets hungrier.
+#[derive(Component, Debug)]
+pub struct HungerClock {
+    pub state: HungerState,
+    pub duration: i32,
+}
+
+/// Food that leaves whoever eats it well fed.
+#[derive(Component, Debug)]
+pub struct ProvidesFood {}
+
+/// Reveals the layout of the whole level when read.
+#[derive(Component, Debug)]
+pub struct MagicMapper {}
+
+/// Lets whoever reads it sense monsters for `turns` turns.
+#[derive(Component, Debug)]
+pub struct DetectsMonsters {
+    pub turns: i32,
+}
+
+/// Senses every monster on the level, seen or not, for `turns_left` more turns.
+#[derive(Component, Debug)]
+pub struct Telepathy {
+    pub turns_left: i32,
+}
@@ -1,5 +1,8 @@
-use super::{CombatStats, Name, Player, SufferDamage, gamelog::GameLog};
-use rltk::console;
+use super::{
+    CombatStats, InBackpack, Name, Player, Position, SufferDamage, Wallet, gamelog::GameLog,
+    spawner,
+};
+use rltk::{RGB, console};
 use specs::prelude::*;
 
 pub struct DamageSystem {}
@@ -37,7 +40,10 @@ pub fn delete_the_dead(ecs: &mut World) {
                     None => {
                         let victim_name = names.get(entity);
                         if let Some(victim_name) = victim_name {
-                            log.entries.push(format!("{} is dead.", &victim_name.name));
+                            log.add_colored(
+                                format!("{} is dead.", &victim_name.name),
+                                RGB::named(rltk::RED),
+                            );
                         }
                         dead.push(entity)
                     }
@@ -47,7 +53,68 @@ pub fn delete_the_dead(ecs: &mut World) {
         }
     }
 
+    // Whatever the dead were carrying spills onto the floor, next to their remains
+    let mut dropped_gold: Vec<(Position, i32)> = Vec::new();
+    let mut corpses: Vec<(Position, String)> = Vec::new();
+    {
+        let entities = ecs.entities();
+        let names = ecs.read_storage::<Name>();
+        let wallets = ecs.read_storage::<Wallet>();
+        let mut positions = ecs.write_storage::<Position>();
+        let mut backpack = ecs.write_storage::<InBackpack>();
+        let mut log = ecs.write_resource::<GameLog>();
+        for victim in dead.iter() {
+            let Some(pos) = positions.get(*victim).cloned() else {
+                continue;
+            };
+
+            if let Some(victim_name) = names.get(*victim) {
+                corpses.push((pos.clone(), victim_name.name.clone()));
+            }
+
+            let mut dropped: Vec<String> = Vec::new();
+            let carried: Vec<Entity> = (&entities, &backpack)
+                .join()
+                .filter(|(_item, pack)| pack.owner == *victim)
+                .map(|(item, _pack)| item)
+                .collect();
+            for item in carried {
+                backpack.remove(item);
+                positions
+                    .insert(item, pos.clone())
+                    .expect("Unable to insert position");
+                if let Some(name) = names.get(item) {
+                    dropped.push(name.name.clone());
+                }
+            }
+            if let Some(wallet) = wallets.get(*victim)
+                && wallet.gold > 0
+            {
+                dropped.push(format!("{} gold", wallet.gold));
+                dropped_gold.push((pos, wallet.gold));
+            }
+
+            if let Some(victim_name) = names.get(*victim)
+                && !dropped.is_empty()
+            {
+                log.add(format!(
+                    "{} drops {}.",
+                    &victim_name.name,
+                    dropped.join(", ")
+                ));
+            }
+        }
+    }
+
     for victim in dead {
         ecs.delete_entity(victim).expect("Unable to delete")
     }
+
+    for (pos, name) in corpses {
+        spawner::corpse(ecs, pos.x, pos.y, &name);
+    }
+
+    for (pos, amount) in dropped_gold {
+        spawner::gold_pile(ecs, pos.x, pos.y, amount);
+    }
 }
@@ -0,0 +1,50 @@
+use super::{Corpse, InBackpack, Name, Telepathy, gamelog::GameLog};
+use specs::prelude::*;
+
+/// Counts down the things that only last a while: corpses rot away, whether they're
+/// lying on the floor or being carried, and telepathy wears off.
+pub struct DecaySystem {}
+
+impl<'a> System<'a> for DecaySystem {
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        WriteStorage<'a, Corpse>,
+        ReadStorage<'a, InBackpack>,
+        ReadStorage<'a, Name>,
+        WriteStorage<'a, Telepathy>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (player_entity, mut log, entities, mut corpses, backpack, names, mut telepathy) = data;
+
+        for (entity, corpse, carried) in (&entities, &mut corpses, backpack.maybe()).join() {
+            corpse.turns_left -= 1;
+            if corpse.turns_left > 0 {
+                continue;
+            }
+
+            entities.delete(entity).expect("Unable to delete corpse");
+            if carried.is_some_and(|pack| pack.owner == *player_entity)
+                && let Some(name) = names.get(entity)
+            {
+                log.add(format!("Your {} rots away.", name.name));
+            }
+        }
+
+        let mut worn_off: Vec<Entity> = Vec::new();
+        for (entity, telepathy) in (&entities, &mut telepathy).join() {
+            telepathy.turns_left -= 1;
+            if telepathy.turns_left <= 0 {
+                worn_off.push(entity);
+            }
+        }
+        for entity in worn_off {
+            telepathy.remove(entity);
+            if entity == *player_entity {
+                log.add("You can no longer sense monsters.");
+            }
+        }
+    }
+}
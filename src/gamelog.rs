@@ -1,3 +1,83 @@
+use rltk::RGB;
+use std::collections::VecDeque;
+
+/// How many entries the log keeps before it starts forgetting the oldest.
+const MAX_ENTRIES: usize = 500;
+
+/// A run of text within a log entry, drawn in one color.
+#[derive(Clone, PartialEq)]
+pub struct LogFragment {
+    pub text: String,
+    pub color: RGB,
+}
+
+impl LogFragment {
+    pub fn new<S: ToString>(text: S, color: RGB) -> LogFragment {
+        LogFragment {
+            text: text.to_string(),
+            color,
+        }
+    }
+
+    /// A fragment in the log's usual white.
+    pub fn plain<S: ToString>(text: S) -> LogFragment {
+        LogFragment::new(text, RGB::named(rltk::WHITE))
+    }
+}
+
+pub struct LogEntry {
+    /// The turn the entry was last logged on.
+    pub turn: i32,
+    pub fragments: Vec<LogFragment>,
+    /// How many times in a row the same message was logged.
+    pub count: i32,
+}
+
+#[derive(Default)]
 pub struct GameLog {
-    pub entries: Vec<String>,
+    entries: VecDeque<LogEntry>,
+    turn: i32,
+}
+
+impl GameLog {
+    /// Sets the turn that entries logged from now on are stamped with.
+    pub fn set_turn(&mut self, turn: i32) {
+        self.turn = turn;
+    }
+
+    /// Logs a message in plain white.
+    pub fn add<S: ToString>(&mut self, text: S) {
+        self.add_fragments(vec![LogFragment::plain(text)]);
+    }
+
+    /// Logs a message all in one color.
+    pub fn add_colored<S: ToString>(&mut self, text: S, color: RGB) {
+        self.add_fragments(vec![LogFragment::new(text, color)]);
+    }
+
+    /// Logs a message made up of differently colored fragments. A message identical
+    /// to the one before it is counted against that entry instead of repeated.
+    pub fn add_fragments(&mut self, fragments: Vec<LogFragment>) {
+        if let Some(last) = self.entries.back_mut()
+            && last.fragments == fragments
+        {
+            last.count += 1;
+            last.turn = self.turn;
+            return;
+        }
+
+        self.entries.push_back(LogEntry {
+            turn: self.turn,
+            fragments,
+            count: 1,
+        });
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Every entry still kept, oldest first.
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &LogEntry> + ExactSizeIterator {
+        self.entries.iter()
+    }
 }
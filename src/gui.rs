@@ -1,4 +1,9 @@
-use super::{CombatStats, InBackpack, Map, Name, Player, Position, State, gamelog::GameLog};
+use super::{
+    CombatStats, Description, Hidden, HungerClock, HungerState, InBackpack, ItemValue, Map, Name,
+    Player, Position, State, Telepathy, TurnCounter, Wallet, camera,
+    gamelog::{GameLog, LogEntry},
+    melee_combat_system, trade,
+};
 use rltk::{Point, RGB, Rltk, VirtualKeyCode};
 use specs::prelude::*;
 
@@ -6,7 +11,35 @@ use specs::prelude::*;
 pub enum ItemMenuResult {
     Cancel,
     NoResponse,
-    // Selected,
+    Selected,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum CharacterSheetResult {
+    Cancel,
+    NoResponse,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum LogMenuResult {
+    Cancel,
+    NoResponse,
+    Scroll(usize),
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum VendorMode {
+    Buy,
+    Sell,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum VendorResult {
+    Cancel,
+    NoResponse,
+    SwitchMode,
+    Buy,
+    Sell,
 }
 
 pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
@@ -19,6 +52,21 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
         RGB::named(rltk::BLACK),
     );
 
+    // Where the player is
+    let depth = ecs.fetch::<Map>().depth;
+    let location = if depth == 0 {
+        String::from("Town")
+    } else {
+        format!("Depth: {}", depth)
+    };
+    ctx.print_color(
+        2,
+        43,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        &location,
+    );
+
     // Player Health
     let combat_stats = ecs.read_storage::<CombatStats>();
     let players = ecs.read_storage::<Player>();
@@ -43,15 +91,42 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
         );
     }
 
+    // Player Gold
+    let wallets = ecs.read_storage::<Wallet>();
+    for (_player, wallet) in (&players, &wallets).join() {
+        ctx.print_color(
+            2,
+            49,
+            RGB::named(rltk::GOLD),
+            RGB::named(rltk::BLACK),
+            format!(" Gold: {} ", wallet.gold),
+        );
+    }
+
+    // Player Hunger
+    let hunger_clocks = ecs.read_storage::<HungerClock>();
+    for (_player, clock) in (&players, &hunger_clocks).join() {
+        let (label, color) = match clock.state {
+            HungerState::WellFed => ("Well Fed", RGB::named(rltk::GREEN)),
+            HungerState::Normal => ("Normal", RGB::named(rltk::WHITE)),
+            HungerState::Hungry => ("Hungry", RGB::named(rltk::ORANGE)),
+            HungerState::Starving => ("Starving", RGB::named(rltk::RED)),
+        };
+        let text = format!(" {} ", label);
+        ctx.print_color(
+            78 - text.len() as i32,
+            49,
+            color,
+            RGB::named(rltk::BLACK),
+            &text,
+        );
+    }
+
     // Game Log
     let log = ecs.fetch::<GameLog>();
 
-    let mut y = 48;
-    for s in log.entries.iter().rev() {
-        if y > 44 {
-            ctx.print(2, y, s);
-        }
-        y -= 1;
+    for (i, entry) in log.entries().rev().take(4).enumerate() {
+        print_log_entry(ctx, 2, 48 - i as i32, entry);
     }
 
     // Tooltips (on cursor hover)
@@ -62,16 +137,19 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
     let map = ecs.fetch::<Map>();
     let names = ecs.read_storage::<Name>();
     let positions = ecs.read_storage::<Position>();
+    let hidden = ecs.read_storage::<Hidden>();
 
     let mouse_pos = ctx.mouse_pos();
-    if mouse_pos.0 > map.width || mouse_pos.1 >= map.height {
+    let Some(mouse_map_pos) = camera::screen_to_world(ecs, Point::new(mouse_pos.0, mouse_pos.1))
+    else {
         return;
-    }
+    };
 
     let mut tooltip: Vec<String> = Vec::new();
-    for (name, position) in (&names, &positions).join() {
+    for (name, position, _hidden) in (&names, &positions, !&hidden).join() {
         let idx = map.xy_idx(position.x, position.y);
-        if position.x == mouse_pos.0 && position.y == mouse_pos.1 && map.visible_tiles[idx] {
+        if position.x == mouse_map_pos.x && position.y == mouse_map_pos.y && map.visible_tiles[idx]
+        {
             tooltip.push(name.name.to_string());
         }
     }
@@ -91,8 +169,8 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
     if mouse_pos.0 > 40 {
         let arrow_pos = Point::new(mouse_pos.0 - 2, mouse_pos.1);
         let left_x = mouse_pos.0 - width;
-        let mut y = mouse_pos.1;
-        for s in tooltip.iter() {
+        for (i, s) in tooltip.iter().enumerate() {
+            let y = mouse_pos.1 + i as i32;
             ctx.print_color(
                 left_x,
                 y,
@@ -107,23 +185,22 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
                     y,
                     RGB::named(rltk::WHITE),
                     RGB::named(rltk::GREY),
-                    &" ".to_string(),
+                    " ",
                 );
             }
-            y += 1;
         }
         ctx.print_color(
             arrow_pos.x,
             arrow_pos.y,
             RGB::named(rltk::WHITE),
             RGB::named(rltk::GREY),
-            &"->".to_string(),
+            "->",
         );
     } else {
         let arrow_pos = Point::new(mouse_pos.0 + 1, mouse_pos.1);
         let left_x = mouse_pos.0 + 3;
-        let mut y = mouse_pos.1;
-        for s in tooltip.iter() {
+        for (i, s) in tooltip.iter().enumerate() {
+            let y = mouse_pos.1 + i as i32;
             ctx.print_color(
                 left_x + 1,
                 y,
@@ -138,32 +215,34 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
                     y,
                     RGB::named(rltk::WHITE),
                     RGB::named(rltk::GREY),
-                    &" ".to_string(),
+                    " ",
                 );
             }
-            y += 1;
         }
         ctx.print_color(
             arrow_pos.x,
             arrow_pos.y,
             RGB::named(rltk::WHITE),
             RGB::named(rltk::GREY),
-            &"<-".to_string(),
+            "<-",
         );
     }
 }
 
-pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> ItemMenuResult {
+pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
     let player_entity = gs.ecs.fetch::<Entity>();
+    let entities = gs.ecs.entities();
     let names = gs.ecs.read_storage::<Name>();
     let backpack = gs.ecs.read_storage::<InBackpack>();
 
-    let inventory = (&backpack, &names)
+    let inventory: Vec<(Entity, &str)> = (&entities, &backpack, &names)
         .join()
-        .filter(|item| item.0.owner == *player_entity);
-    let count = inventory.count();
+        .filter(|(_entity, pack, _name)| pack.owner == *player_entity)
+        .map(|(entity, _pack, name)| (entity, name.name.as_str()))
+        .collect();
+    let count = inventory.len();
 
-    let mut y = (25 - (count / 2)) as i32;
+    let y = (25 - (count / 2)) as i32;
     ctx.draw_box(
         15,
         y - 2,
@@ -187,11 +266,8 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> ItemMenuResult {
         "ESCAPE to cancel",
     );
 
-    let mut i = 0;
-    for (_pack, name) in (&backpack, &names)
-        .join()
-        .filter(|item| item.0.owner == *player_entity)
-    {
+    for (i, (_item, name)) in inventory.iter().enumerate() {
+        let y = y + i as i32;
         ctx.set(
             17,
             y,
@@ -214,16 +290,456 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> ItemMenuResult {
             rltk::to_cp437(')'),
         );
 
-        ctx.print(21, y, &name.name.to_string());
-        y += 1;
-        i += i;
+        ctx.print(21, y, name);
     }
 
     match ctx.key {
-        None => ItemMenuResult::NoResponse,
+        None => (ItemMenuResult::NoResponse, None),
+        Some(VirtualKeyCode::Escape) => (ItemMenuResult::Cancel, None),
+        Some(key) => {
+            let selection = rltk::letter_to_option(key);
+            if selection < 0 || selection as usize >= count {
+                return (ItemMenuResult::NoResponse, None);
+            }
+            (
+                ItemMenuResult::Selected,
+                Some(inventory[selection as usize].0),
+            )
+        }
+    }
+}
+
+/// The player's stats, what is affecting them and where they are.
+pub fn show_character(ecs: &World, ctx: &mut Rltk) -> CharacterSheetResult {
+    let player_entity = ecs.fetch::<Entity>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let wallets = ecs.read_storage::<Wallet>();
+    let hunger_clocks = ecs.read_storage::<HungerClock>();
+    let telepathy = ecs.read_storage::<Telepathy>();
+    let depth = ecs.fetch::<Map>().depth;
+    let turn = ecs.fetch::<TurnCounter>().turn;
+
+    let mut lines: Vec<(String, RGB)> = Vec::new();
+    if let Some(stats) = combat_stats.get(*player_entity) {
+        lines.push((
+            format!("Health:  {} / {}", stats.hp, stats.max_hp),
+            RGB::named(rltk::WHITE),
+        ));
+        lines.push((
+            format!("Power:   {}", melee_combat_system::attack_power(stats)),
+            RGB::named(rltk::WHITE),
+        ));
+        lines.push((
+            format!("Defense: {}", melee_combat_system::defense(stats)),
+            RGB::named(rltk::WHITE),
+        ));
+    }
+    if let Some(wallet) = wallets.get(*player_entity) {
+        lines.push((format!("Gold:    {}", wallet.gold), RGB::named(rltk::GOLD)));
+    }
+    lines.push((String::new(), RGB::named(rltk::WHITE)));
+
+    let location = if depth == 0 {
+        String::from("In town")
+    } else {
+        format!("Depth {} of the dungeon", depth)
+    };
+    lines.push((location, RGB::named(rltk::WHITE)));
+    lines.push((format!("Turn {}", turn), RGB::named(rltk::WHITE)));
+    lines.push((String::new(), RGB::named(rltk::WHITE)));
+
+    lines.push((String::from("Status effects:"), RGB::named(rltk::YELLOW)));
+    let mut effects: Vec<(String, RGB)> = Vec::new();
+    if let Some(clock) = hunger_clocks.get(*player_entity) {
+        match clock.state {
+            HungerState::WellFed => effects.push((
+                format!("Well Fed ({} turns)", clock.duration),
+                RGB::named(rltk::GREEN),
+            )),
+            HungerState::Normal => {}
+            HungerState::Hungry => effects.push((String::from("Hungry"), RGB::named(rltk::ORANGE))),
+            HungerState::Starving => {
+                effects.push((String::from("Starving"), RGB::named(rltk::RED)))
+            }
+        }
+    }
+    if let Some(telepathy) = telepathy.get(*player_entity) {
+        effects.push((
+            format!("Telepathic ({} turns)", telepathy.turns_left),
+            RGB::named(rltk::CYAN),
+        ));
+    }
+    if effects.is_empty() {
+        effects.push((String::from("None"), RGB::named(rltk::GREY)));
+    }
+    lines.extend(
+        effects
+            .into_iter()
+            .map(|(text, color)| (format!("  {}", text), color)),
+    );
+
+    let height = lines.len() as i32 + 1;
+    let y = 21 - height / 2;
+    ctx.draw_box(
+        20,
+        y,
+        39,
+        height,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        23,
+        y,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Character",
+    );
+    ctx.print_color(
+        23,
+        y + height,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "ESCAPE to close",
+    );
+    for (i, (text, color)) in lines.iter().enumerate() {
+        ctx.print_color(22, y + 1 + i as i32, *color, RGB::named(rltk::BLACK), text);
+    }
+
+    match ctx.key {
+        Some(VirtualKeyCode::Escape) | Some(VirtualKeyCode::C) => CharacterSheetResult::Cancel,
+        _ => CharacterSheetResult::NoResponse,
+    }
+}
+
+/// Prints a log entry's fragments one after another, noting how many times it
+/// was repeated.
+fn print_log_entry(ctx: &mut Rltk, x: i32, y: i32, entry: &LogEntry) {
+    let mut x = x;
+    for fragment in entry.fragments.iter() {
+        ctx.print_color(
+            x,
+            y,
+            fragment.color,
+            RGB::named(rltk::BLACK),
+            &fragment.text,
+        );
+        x += fragment.text.len() as i32;
+    }
+    if entry.count > 1 {
+        ctx.print_color(
+            x,
+            y,
+            RGB::named(rltk::GREY),
+            RGB::named(rltk::BLACK),
+            format!(" x{}", entry.count),
+        );
+    }
+}
+
+/// The whole message log, newest at the bottom, with the turn each message was
+/// logged on. `scroll` is how many of the newest entries are scrolled out of view.
+pub fn show_log(ecs: &World, ctx: &mut Rltk, scroll: usize) -> LogMenuResult {
+    const PAGE: usize = 47;
+
+    let log = ecs.fetch::<GameLog>();
+    let max_scroll = log.entries().len().saturating_sub(PAGE);
+    let scroll = scroll.min(max_scroll);
+
+    ctx.draw_box(
+        0,
+        0,
+        79,
+        49,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        3,
+        0,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Message Log",
+    );
+    ctx.print_color(
+        3,
+        49,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "UP/DOWN or PGUP/PGDN to scroll, ESCAPE to close",
+    );
+
+    for (i, entry) in log.entries().rev().skip(scroll).take(PAGE).enumerate() {
+        let y = 48 - i as i32;
+        ctx.print_color(
+            2,
+            y,
+            RGB::named(rltk::GREY),
+            RGB::named(rltk::BLACK),
+            format!("{:>5}", entry.turn),
+        );
+        print_log_entry(ctx, 9, y, entry);
+    }
+
+    match ctx.key {
+        None => LogMenuResult::NoResponse,
         Some(key) => match key {
-            VirtualKeyCode::Escape => ItemMenuResult::Cancel,
-            _ => ItemMenuResult::NoResponse,
+            VirtualKeyCode::Escape | VirtualKeyCode::M => LogMenuResult::Cancel,
+            VirtualKeyCode::Up | VirtualKeyCode::K => {
+                LogMenuResult::Scroll((scroll + 1).min(max_scroll))
+            }
+            VirtualKeyCode::Down | VirtualKeyCode::J => {
+                LogMenuResult::Scroll(scroll.saturating_sub(1))
+            }
+            VirtualKeyCode::PageUp => LogMenuResult::Scroll((scroll + PAGE).min(max_scroll)),
+            VirtualKeyCode::PageDown => LogMenuResult::Scroll(scroll.saturating_sub(PAGE)),
+            _ => LogMenuResult::NoResponse,
         },
     }
 }
+
+/// Highlights the look cursor and describes what is under it: the tile, and
+/// everything in view standing on it. Tiles out of sight are described as
+/// remembered, without the entities on them.
+pub fn draw_look(ecs: &World, ctx: &mut Rltk, cursor: Point) {
+    const BOX_WIDTH: i32 = 38;
+    const TEXT_WIDTH: usize = BOX_WIDTH as usize - 3;
+
+    let map = ecs.fetch::<Map>();
+    let entities = ecs.entities();
+    let positions = ecs.read_storage::<Position>();
+    let names = ecs.read_storage::<Name>();
+    let descriptions = ecs.read_storage::<Description>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let hidden = ecs.read_storage::<Hidden>();
+
+    let origin = camera::get_screen_bounds(ecs);
+    let screen = Point::new(cursor.x - origin.x, cursor.y - origin.y);
+    ctx.set_bg(screen.x, screen.y, RGB::named(rltk::MAGENTA));
+
+    // Each line, with the health of the monster it names, if it is one
+    let mut lines: Vec<(String, RGB, Option<&CombatStats>)> = Vec::new();
+    let idx = map.xy_idx(cursor.x, cursor.y);
+    if map.visible_tiles[idx] {
+        for (entity, pos, name, _hidden) in (&entities, &positions, &names, !&hidden).join() {
+            if pos.x != cursor.x || pos.y != cursor.y {
+                continue;
+            }
+            lines.push((
+                name.name.clone(),
+                RGB::named(rltk::YELLOW),
+                combat_stats.get(entity),
+            ));
+            if let Some(description) = descriptions.get(entity) {
+                for line in wrap_text(&description.text, TEXT_WIDTH) {
+                    lines.push((line, RGB::named(rltk::WHITE), None));
+                }
+            }
+        }
+        for line in wrap_text(map.tiles[idx].description(), TEXT_WIDTH) {
+            lines.push((line, RGB::named(rltk::GREY), None));
+        }
+    } else if map.revealed_tiles[idx] {
+        let remembered = format!("You remember: {}", map.tiles[idx].description());
+        for line in wrap_text(&remembered, TEXT_WIDTH) {
+            lines.push((line, RGB::named(rltk::GREY), None));
+        }
+    } else {
+        lines.push((
+            String::from("You can't see there."),
+            RGB::named(rltk::GREY),
+            None,
+        ));
+    }
+
+    // Keep the box on the other half of the screen from the cursor
+    let box_x = if screen.x > camera::VIEW_WIDTH / 2 {
+        1
+    } else {
+        camera::VIEW_WIDTH - BOX_WIDTH - 2
+    };
+    let height = lines.len() as i32 + lines.iter().filter(|line| line.2.is_some()).count() as i32;
+    ctx.draw_box(
+        box_x,
+        1,
+        BOX_WIDTH,
+        height + 1,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        box_x + 2,
+        1,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Look",
+    );
+    ctx.print_color(
+        box_x + 2,
+        height + 2,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "ESCAPE to stop looking",
+    );
+
+    let mut y = 2;
+    for (text, color, stats) in lines.iter() {
+        ctx.print_color(box_x + 2, y, *color, RGB::named(rltk::BLACK), text);
+        y += 1;
+        if let Some(stats) = stats {
+            ctx.draw_bar_horizontal(
+                box_x + 2,
+                y,
+                TEXT_WIDTH,
+                stats.hp,
+                stats.max_hp,
+                RGB::named(rltk::RED),
+                RGB::named(rltk::BLACK),
+            );
+            y += 1;
+        }
+    }
+}
+
+/// Splits `text` into lines of at most `width` characters, breaking between words.
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.len() + 1 + word.len() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// The trade screen: what the vendor has for sale, or what the player could sell
+/// them, with prices. Returns the item picked, if any.
+pub fn show_vendor(
+    gs: &mut State,
+    ctx: &mut Rltk,
+    vendor: Entity,
+    mode: VendorMode,
+) -> (VendorResult, Option<Entity>) {
+    let player_entity = gs.ecs.fetch::<Entity>();
+    let entities = gs.ecs.entities();
+    let names = gs.ecs.read_storage::<Name>();
+    let backpack = gs.ecs.read_storage::<InBackpack>();
+    let values = gs.ecs.read_storage::<ItemValue>();
+    let wallets = gs.ecs.read_storage::<Wallet>();
+
+    let vendor_name = names
+        .get(vendor)
+        .map_or("vendor", |name| name.name.as_str());
+    let (owner, title, switch_hint) = match mode {
+        VendorMode::Buy => (
+            vendor,
+            format!("Buy from the {}", vendor_name),
+            "TAB to sell",
+        ),
+        VendorMode::Sell => (
+            *player_entity,
+            format!("Sell to the {}", vendor_name),
+            "TAB to buy",
+        ),
+    };
+
+    // Only things with a value can be traded; corpses, for one, are worthless
+    let wares: Vec<(Entity, &str, i32)> = (&entities, &backpack, &names, &values)
+        .join()
+        .filter(|(_entity, pack, _name, _value)| pack.owner == owner)
+        .map(|(entity, _pack, name, value)| {
+            let price = match mode {
+                VendorMode::Buy => trade::buy_price(value),
+                VendorMode::Sell => trade::sell_price(value),
+            };
+            (entity, name.name.as_str(), price)
+        })
+        .collect();
+    let count = wares.len();
+
+    let y = (25 - (count / 2)) as i32;
+    ctx.draw_box(
+        15,
+        y - 2,
+        51,
+        count + 3,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        18,
+        y - 2,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        &title,
+    );
+    if let Some(wallet) = wallets.get(*player_entity) {
+        ctx.print_color(
+            50,
+            y - 2,
+            RGB::named(rltk::GOLD),
+            RGB::named(rltk::BLACK),
+            format!("You have {} gold", wallet.gold),
+        );
+    }
+    ctx.print_color(
+        18,
+        y + count as i32,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        format!("ESCAPE to leave, {}", switch_hint),
+    );
+
+    for (i, (_item, name, price)) in wares.iter().enumerate() {
+        let y = y + i as i32;
+        ctx.set(
+            17,
+            y,
+            RGB::named(rltk::WHITE),
+            RGB::named(rltk::BLACK),
+            rltk::to_cp437('('),
+        );
+        ctx.set(
+            18,
+            y,
+            RGB::named(rltk::YELLOW),
+            RGB::named(rltk::BLACK),
+            97 + i as rltk::FontCharType,
+        );
+        ctx.set(
+            19,
+            y,
+            RGB::named(rltk::WHITE),
+            RGB::named(rltk::BLACK),
+            rltk::to_cp437(')'),
+        );
+
+        ctx.print(21, y, name);
+        ctx.print(56, y, format!("{:>4} gold", price));
+    }
+
+    match ctx.key {
+        None => (VendorResult::NoResponse, None),
+        Some(VirtualKeyCode::Escape) => (VendorResult::Cancel, None),
+        Some(VirtualKeyCode::Tab) => (VendorResult::SwitchMode, None),
+        Some(key) => {
+            let selection = rltk::letter_to_option(key);
+            if selection < 0 || selection as usize >= count {
+                return (VendorResult::NoResponse, None);
+            }
+            let item = Some(wares[selection as usize].0);
+            match mode {
+                VendorMode::Buy => (VendorResult::Buy, item),
+                VendorMode::Sell => (VendorResult::Sell, item),
+            }
+        }
+    }
+}
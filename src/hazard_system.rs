@@ -0,0 +1,42 @@
+use super::{CombatStats, Map, Name, Position, SufferDamage, gamelog::GameLog};
+use rltk::RGB;
+use specs::prelude::*;
+
+/// Hurts everything standing on a damaging tile, such as lava, once per turn.
+pub struct HazardSystem {}
+
+impl<'a> System<'a> for HazardSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Map>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, Name>,
+        WriteStorage<'a, SufferDamage>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (map, mut log, entities, positions, combat_stats, names, mut suffer_damage) = data;
+
+        for (entity, pos, _stats) in (&entities, &positions, &combat_stats).join() {
+            let idx = map.xy_idx(pos.x, pos.y);
+            let tile = map.tiles[idx];
+            let damage = tile.damage_per_turn();
+            if damage == 0 {
+                continue;
+            }
+
+            SufferDamage::new_damage(&mut suffer_damage, entity, damage);
+            if map.visible_tiles[idx]
+                && let Some(name) = names.get(entity)
+            {
+                log.add_colored(
+                    format!("{} {}", &name.name, tile.damage_message()),
+                    RGB::named(rltk::ORANGE),
+                );
+            }
+        }
+    }
+}
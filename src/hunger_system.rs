@@ -0,0 +1,154 @@
+use super::{HungerClock, HungerState, SufferDamage, gamelog::GameLog};
+use rltk::RGB;
+use specs::prelude::*;
+
+/// Turns spent in each hunger state before moving on to the next.
+const WELL_FED_TURNS: i32 = 20;
+const NORMAL_TURNS: i32 = 200;
+const HUNGRY_TURNS: i32 = 200;
+
+/// Damage taken each turn while starving.
+const STARVATION_DAMAGE: i32 = 1;
+
+/// Makes everything with a hunger clock a little hungrier each turn, and hurts
+/// anything that is starving.
+pub struct HungerSystem {}
+
+/// The hunger clock of something that has just eaten.
+pub fn well_fed() -> HungerClock {
+    HungerClock {
+        state: HungerState::WellFed,
+        duration: WELL_FED_TURNS,
+    }
+}
+
+/// Counts a turn off a clock that isn't starving yet, moving it on to the next
+/// state when its time is up. Returns what to tell the player when it does.
+fn tick(clock: &mut HungerClock) -> Option<&'static str> {
+    clock.duration -= 1;
+    if clock.duration > 0 {
+        return None;
+    }
+
+    let message = match clock.state {
+        HungerState::WellFed => {
+            clock.state = HungerState::Normal;
+            clock.duration = NORMAL_TURNS;
+            "You are no longer well fed."
+        }
+        HungerState::Normal => {
+            clock.state = HungerState::Hungry;
+            clock.duration = HUNGRY_TURNS;
+            "You are hungry."
+        }
+        HungerState::Hungry | HungerState::Starving => {
+            clock.state = HungerState::Starving;
+            clock.duration = 0;
+            "You are starving!"
+        }
+    };
+    Some(message)
+}
+
+impl<'a> System<'a> for HungerSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        WriteStorage<'a, HungerClock>,
+        WriteStorage<'a, SufferDamage>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (player_entity, mut log, entities, mut hunger_clocks, mut suffer_damage) = data;
+
+        for (entity, clock) in (&entities, &mut hunger_clocks).join() {
+            let is_player = entity == *player_entity;
+
+            if clock.state == HungerState::Starving {
+                SufferDamage::new_damage(&mut suffer_damage, entity, STARVATION_DAMAGE);
+                if is_player {
+                    log.add_colored(
+                        format!(
+                            "Your hunger pangs are getting painful! You suffer {} hp damage.",
+                            STARVATION_DAMAGE
+                        ),
+                        RGB::named(rltk::RED),
+                    );
+                }
+                continue;
+            }
+
+            let Some(message) = tick(clock) else {
+                continue;
+            };
+            if is_player {
+                log.add_colored(message, RGB::named(rltk::ORANGE));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ticks `clock` until it changes state, returning how many turns that took.
+    fn turns_until_next_state(clock: &mut HungerClock) -> i32 {
+        let state = clock.state;
+        let mut turns = 0;
+        while clock.state == state {
+            tick(clock);
+            turns += 1;
+        }
+        turns
+    }
+
+    #[test]
+    fn hunger_runs_through_every_state() {
+        let mut clock = well_fed();
+        assert_eq!(turns_until_next_state(&mut clock), WELL_FED_TURNS);
+        assert_eq!(clock.state, HungerState::Normal);
+        assert_eq!(turns_until_next_state(&mut clock), NORMAL_TURNS);
+        assert_eq!(clock.state, HungerState::Hungry);
+        assert_eq!(turns_until_next_state(&mut clock), HUNGRY_TURNS);
+        assert_eq!(clock.state, HungerState::Starving);
+    }
+
+    #[test]
+    fn only_a_change_of_state_is_reported() {
+        let mut clock = HungerClock {
+            state: HungerState::Normal,
+            duration: 2,
+        };
+        assert_eq!(tick(&mut clock), None);
+        assert_eq!(tick(&mut clock), Some("You are hungry."));
+        assert_eq!(clock.duration, HUNGRY_TURNS);
+    }
+
+    #[test]
+    fn starving_players_take_damage_every_turn() {
+        let mut ecs = World::new();
+        ecs.register::<HungerClock>();
+        ecs.register::<SufferDamage>();
+        ecs.insert(GameLog::default());
+        let player = ecs
+            .create_entity()
+            .with(HungerClock {
+                state: HungerState::Starving,
+                duration: 0,
+            })
+            .build();
+        ecs.insert(player);
+
+        HungerSystem {}.run_now(&ecs);
+
+        let damage = ecs.read_storage::<SufferDamage>();
+        assert_eq!(
+            damage.get(player).map(|d| d.amount.clone()),
+            Some(vec![STARVATION_DAMAGE])
+        );
+        assert_eq!(ecs.fetch::<GameLog>().entries().count(), 1);
+    }
+}
@@ -1,4 +1,10 @@
-use super::{InBackpack, Name, Position, WantsToPickUpItem, gamelog::GameLog};
+use super::{
+    CombatStats, DetectsMonsters, GoldPile, HealingPotion, HungerClock, InBackpack, MagicMapper,
+    Name, Position, ProvidesFood, RunState, Telepathy, Wallet, WantsToPickUpItem, WantsToUseItem,
+    gamelog::{GameLog, LogFragment},
+    hunger_system,
+};
+use rltk::RGB;
 use specs::prelude::*;
 
 pub struct ItemCollectionSystem {}
@@ -12,13 +18,43 @@ impl<'a> System<'a> for ItemCollectionSystem {
         WriteStorage<'a, Position>,
         ReadStorage<'a, Name>,
         WriteStorage<'a, InBackpack>,
+        ReadStorage<'a, GoldPile>,
+        WriteStorage<'a, Wallet>,
+        Entities<'a>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (player_entity, mut gamelog, mut wants_pickup, mut positions, names, mut backpack) =
-            data;
+        let (
+            player_entity,
+            mut gamelog,
+            mut wants_pickup,
+            mut positions,
+            names,
+            mut backpack,
+            gold_piles,
+            mut wallets,
+            entities,
+        ) = data;
 
         for pickup in wants_pickup.join() {
+            // Gold goes straight into the wallet rather than taking up backpack space
+            if let Some(gold) = gold_piles.get(pickup.item)
+                && let Some(wallet) = wallets.get_mut(pickup.collected_by)
+            {
+                wallet.gold += gold.amount;
+                entities
+                    .delete(pickup.item)
+                    .expect("Unable to delete gold pile");
+                if pickup.collected_by == *player_entity {
+                    gamelog.add_fragments(vec![
+                        LogFragment::plain("You pick up "),
+                        LogFragment::new(format!("{} gold", gold.amount), RGB::named(rltk::GOLD)),
+                        LogFragment::plain("."),
+                    ]);
+                }
+                continue;
+            }
+
             positions.remove(pickup.item);
             backpack
                 .insert(
@@ -30,13 +66,121 @@ impl<'a> System<'a> for ItemCollectionSystem {
                 .expect("Unable to insert backpack entry.");
 
             if pickup.collected_by == *player_entity {
-                gamelog.entries.push(format!(
-                    "You pick up the {}.",
-                    names.get(pickup.item).unwrap().name
-                ));
+                gamelog.add_fragments(vec![
+                    LogFragment::plain("You pick up the "),
+                    LogFragment::new(
+                        &names.get(pickup.item).unwrap().name,
+                        RGB::named(rltk::GREEN),
+                    ),
+                    LogFragment::plain("."),
+                ]);
             }
         }
 
         wants_pickup.clear();
     }
 }
+
+/// Eats, drinks or reads whatever items have been picked from an inventory, using
+/// them up.
+pub struct ItemUseSystem {}
+
+impl<'a> System<'a> for ItemUseSystem {
+    #![allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToUseItem>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, HealingPotion>,
+        ReadStorage<'a, ProvidesFood>,
+        WriteStorage<'a, CombatStats>,
+        WriteStorage<'a, HungerClock>,
+        ReadStorage<'a, MagicMapper>,
+        ReadStorage<'a, DetectsMonsters>,
+        WriteStorage<'a, Telepathy>,
+        WriteExpect<'a, RunState>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            mut gamelog,
+            entities,
+            mut wants_use,
+            names,
+            healing_potions,
+            food,
+            mut combat_stats,
+            mut hunger_clocks,
+            magic_mappers,
+            detects_monsters,
+            mut telepathy,
+            mut runstate,
+        ) = data;
+
+        for (user, use_item) in (&entities, &wants_use).join() {
+            let item_name = names
+                .get(use_item.item)
+                .map_or("item", |name| name.name.as_str());
+            let mut used = false;
+
+            if let Some(potion) = healing_potions.get(use_item.item)
+                && let Some(stats) = combat_stats.get_mut(user)
+            {
+                stats.hp = i32::min(stats.max_hp, stats.hp + potion.heal_amount);
+                used = true;
+                if user == *player_entity {
+                    gamelog.add_fragments(vec![
+                        LogFragment::plain(format!("You drink the {}, healing ", item_name)),
+                        LogFragment::new(
+                            format!("{} hp", potion.heal_amount),
+                            RGB::named(rltk::GREEN),
+                        ),
+                        LogFragment::plain("."),
+                    ]);
+                }
+            }
+
+            if food.get(use_item.item).is_some()
+                && let Some(clock) = hunger_clocks.get_mut(user)
+            {
+                *clock = hunger_system::well_fed();
+                used = true;
+                if user == *player_entity {
+                    gamelog.add(format!("You eat the {}.", item_name));
+                }
+            }
+
+            if magic_mappers.get(use_item.item).is_some() && user == *player_entity {
+                *runstate = RunState::MagicMapReveal { row: 0 };
+                used = true;
+                gamelog.add("The map is revealed to you!");
+            }
+
+            if let Some(detects) = detects_monsters.get(use_item.item) {
+                telepathy
+                    .insert(
+                        user,
+                        Telepathy {
+                            turns_left: detects.turns,
+                        },
+                    )
+                    .expect("Unable to insert telepathy");
+                used = true;
+                if user == *player_entity {
+                    gamelog.add("You sense the presence of monsters!");
+                }
+            }
+
+            if used {
+                entities
+                    .delete(use_item.item)
+                    .expect("Unable to delete used item");
+            }
+        }
+
+        wants_use.clear();
+    }
+}
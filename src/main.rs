@@ -1,6 +1,8 @@
 use rltk::{GameState, Point, Rltk};
 use specs::prelude::*;
 
+mod autopilot;
+mod camera;
 mod gamelog;
 mod gui;
 mod spawner;
@@ -11,6 +13,8 @@ pub use components::*;
 mod map;
 pub use map::*;
 
+mod map_builders;
+
 mod player;
 use player::*;
 
@@ -33,7 +37,24 @@ mod damage_system;
 pub use damage_system::DamageSystem;
 
 mod inventory_system;
-pub use inventory_system::ItemCollectionSystem;
+pub use inventory_system::{ItemCollectionSystem, ItemUseSystem};
+
+mod trigger_system;
+pub use trigger_system::TriggerSystem;
+
+mod hazard_system;
+pub use hazard_system::HazardSystem;
+
+mod decay_system;
+pub use decay_system::DecaySystem;
+
+mod hunger_system;
+pub use hunger_system::HungerSystem;
+
+mod regeneration_system;
+pub use regeneration_system::RegenerationSystem;
+
+mod trade;
 
 use crate::gui::draw_ui;
 
@@ -44,12 +65,46 @@ pub enum RunState {
     PlayerTurn,
     MonsterTurn,
     ShowInventory,
+    MapGeneration,
+    ShowVendor {
+        vendor: Entity,
+        mode: gui::VendorMode,
+    },
+    NextLevel,
+    MagicMapReveal {
+        row: i32,
+    },
+    LookMode {
+        cursor: Point,
+    },
+    ShowLog {
+        scroll: usize,
+    },
+    ShowCharacter,
+}
+
+/// How many turns the player has taken. Things that happen every so many turns,
+/// and the log's timestamps, go by this.
+#[derive(Default)]
+pub struct TurnCounter {
+    pub turn: i32,
 }
 
 pub struct State {
     ecs: World,
+    map_builder: Option<String>,
+    show_mapgen: bool,
+    mapgen_next_state: RunState,
+    mapgen_history: Vec<map_builders::MapSnapshot>,
+    mapgen_index: usize,
+    mapgen_timer: f32,
 }
 
+/// The size of the town and of each generated dungeon level. Maps can be larger than the screen;
+/// the camera follows the player around them.
+const MAP_WIDTH: i32 = 120;
+const MAP_HEIGHT: i32 = 70;
+
 impl State {
     fn run_systems(&mut self) {
         let mut vis = VisibilitySystem {};
@@ -61,6 +116,9 @@ impl State {
         let mut mapindex = MapIndexingSystem {};
         mapindex.run_now(&self.ecs);
 
+        let mut triggers = TriggerSystem {};
+        triggers.run_now(&self.ecs);
+
         let mut melee_combat = MeleeCombatSystem {};
         melee_combat.run_now(&self.ecs);
 
@@ -70,8 +128,105 @@ impl State {
         let mut pickup_system = ItemCollectionSystem {};
         pickup_system.run_now(&self.ecs);
 
+        let mut item_use = ItemUseSystem {};
+        item_use.run_now(&self.ecs);
+
         self.ecs.maintain();
     }
+
+    /// Runs the systems that move things along by a whole turn. Unlike `run_systems`,
+    /// which runs for both the player's and the monsters' half of a turn, these run
+    /// once per turn, before the player's half.
+    fn run_turn_systems(&mut self) {
+        let mut hazards = HazardSystem {};
+        hazards.run_now(&self.ecs);
+
+        let mut decay = DecaySystem {};
+        decay.run_now(&self.ecs);
+
+        let mut hunger = HungerSystem {};
+        hunger.run_now(&self.ecs);
+
+        let mut regeneration = RegenerationSystem {};
+        regeneration.run_now(&self.ecs);
+    }
+
+    /// Builds the map for `new_depth`, spawns its contents and moves the player to
+    /// its start. Depth 0 is the town; below that are the dungeon levels.
+    fn generate_world_map(&mut self, new_depth: i32) {
+        let mut builder = if new_depth == 0 {
+            map_builders::town_builder(MAP_WIDTH, MAP_HEIGHT)
+        } else {
+            match &self.map_builder {
+                Some(name) => map_builders::builder_by_name(name, MAP_WIDTH, MAP_HEIGHT)
+                    .expect("Unknown map builder"),
+                None => {
+                    let mut rng = self.ecs.write_resource::<rltk::RandomNumberGenerator>();
+                    map_builders::random_builder(&mut rng, MAP_WIDTH, MAP_HEIGHT)
+                }
+            }
+        };
+        if self.show_mapgen {
+            builder.record_snapshots();
+        }
+        builder.build_map();
+        self.mapgen_history = builder.get_snapshot_history();
+        self.mapgen_index = 0;
+        self.mapgen_timer = 0.0;
+        let mut map = builder.get_map();
+        map.depth = new_depth;
+        self.ecs.insert(map);
+        builder.spawn_entities(&mut self.ecs);
+
+        // Place the player at the builder's starting position
+        let player_start = builder.get_starting_position();
+        let mut player_position = self.ecs.write_resource::<Point>();
+        *player_position = Point::new(player_start.x, player_start.y);
+        let mut position_components = self.ecs.write_storage::<Position>();
+        let player_entity = self.ecs.fetch::<Entity>();
+        if let Some(player_pos_comp) = position_components.get_mut(*player_entity) {
+            player_pos_comp.x = player_start.x;
+            player_pos_comp.y = player_start.y;
+        }
+
+        // Mark the player's visibility as dirty
+        let mut viewshed_components = self.ecs.write_storage::<Viewshed>();
+        if let Some(viewshed) = viewshed_components.get_mut(*player_entity) {
+            viewshed.dirty = true;
+        }
+    }
+
+    /// Everything except the player and what they are carrying stays behind when
+    /// they leave a level.
+    fn entities_to_remove_on_level_change(&self) -> Vec<Entity> {
+        let entities = self.ecs.entities();
+        let player_entity = self.ecs.fetch::<Entity>();
+        let backpack = self.ecs.read_storage::<InBackpack>();
+
+        entities
+            .join()
+            .filter(|entity| {
+                *entity != *player_entity
+                    && backpack
+                        .get(*entity)
+                        .is_none_or(|pack| pack.owner != *player_entity)
+            })
+            .collect()
+    }
+
+    fn goto_next_level(&mut self) {
+        for target in self.entities_to_remove_on_level_change() {
+            self.ecs
+                .delete_entity(target)
+                .expect("Unable to delete entity");
+        }
+
+        let current_depth = self.ecs.fetch::<Map>().depth;
+        self.generate_world_map(current_depth + 1);
+
+        let mut gamelog = self.ecs.fetch_mut::<gamelog::GameLog>();
+        gamelog.add("You descend to the next level.");
+    }
 }
 
 impl GameState for State {
@@ -93,17 +248,70 @@ impl GameState for State {
             RunState::AwaitingInput => {
                 newrunstate = player_input(self, ctx);
             }
+            RunState::LookMode { cursor } => {
+                newrunstate = look_input(self, ctx, cursor);
+            }
             RunState::PlayerTurn => {
+                let turn = {
+                    let mut turns = self.ecs.write_resource::<TurnCounter>();
+                    turns.turn += 1;
+                    turns.turn
+                };
+                self.ecs.fetch_mut::<gamelog::GameLog>().set_turn(turn);
+                self.run_turn_systems();
                 self.run_systems();
-                newrunstate = RunState::MonsterTurn;
+                // Using an item may have started something that plays out over several frames
+                newrunstate = match *self.ecs.fetch::<RunState>() {
+                    RunState::MagicMapReveal { .. } => RunState::MagicMapReveal { row: 0 },
+                    _ => RunState::MonsterTurn,
+                };
             }
             RunState::MonsterTurn => {
                 self.run_systems();
                 newrunstate = RunState::AwaitingInput;
             }
-            RunState::ShowInventory => {
+            RunState::ShowInventory
+            | RunState::ShowVendor { .. }
+            | RunState::ShowLog { .. }
+            | RunState::ShowCharacter => {
                 // Render will happen *after* entity drawing
             }
+            RunState::MagicMapReveal { row } => {
+                // Uncover the level one row per frame
+                let mut map = self.ecs.fetch_mut::<Map>();
+                for x in 0..map.width {
+                    let idx = map.xy_idx(x, row);
+                    map.revealed_tiles[idx] = true;
+                }
+                newrunstate = if row >= map.height - 1 {
+                    RunState::MonsterTurn
+                } else {
+                    RunState::MagicMapReveal { row: row + 1 }
+                };
+            }
+            RunState::NextLevel => {
+                self.goto_next_level();
+                self.mapgen_next_state = RunState::PreRun;
+                newrunstate = RunState::MapGeneration;
+            }
+            RunState::MapGeneration => {
+                const SNAPSHOT_DELAY_MS: f32 = 200.0;
+
+                if !self.show_mapgen || self.mapgen_history.is_empty() {
+                    newrunstate = self.mapgen_next_state;
+                } else {
+                    camera::render_debug_map(&self.mapgen_history[self.mapgen_index], ctx);
+
+                    self.mapgen_timer += ctx.frame_time_ms;
+                    if self.mapgen_timer > SNAPSHOT_DELAY_MS {
+                        self.mapgen_timer = 0.0;
+                        self.mapgen_index += 1;
+                        if self.mapgen_index >= self.mapgen_history.len() {
+                            newrunstate = self.mapgen_next_state;
+                        }
+                    }
+                }
+            }
         }
 
         {
@@ -111,33 +319,78 @@ impl GameState for State {
             *runwriter = newrunstate;
         }
 
+        // The map generation visualizer draws its own frames
+        if newrunstate == RunState::MapGeneration {
+            return;
+        }
+
         // Render game map and entities
-        draw_map(&self.ecs, ctx);
+        camera::render_camera(&self.ecs, ctx);
+
+        draw_ui(&self.ecs, ctx);
 
+        if let RunState::LookMode { cursor } = newrunstate {
+            gui::draw_look(&self.ecs, ctx, cursor);
+        }
+
+        if let RunState::ShowLog { scroll } = newrunstate {
+            let next_state = match gui::show_log(&self.ecs, ctx, scroll) {
+                gui::LogMenuResult::Cancel => RunState::AwaitingInput,
+                gui::LogMenuResult::Scroll(scroll) => RunState::ShowLog { scroll },
+                gui::LogMenuResult::NoResponse => newrunstate,
+            };
+            let mut runwriter = self.ecs.write_resource::<RunState>();
+            *runwriter = next_state;
+        }
+
+        if newrunstate == RunState::ShowCharacter
+            && gui::show_character(&self.ecs, ctx) == gui::CharacterSheetResult::Cancel
         {
-            let positions = self.ecs.read_storage::<Position>();
-            let renderables = self.ecs.read_storage::<Renderable>();
-            let map = self.ecs.fetch::<Map>();
-
-            for (pos, render) in (&positions, &renderables).join() {
-                let idx = map.xy_idx(pos.x, pos.y);
-                if map.visible_tiles[idx] {
-                    ctx.set(pos.x, pos.y, render.fg, render.bg, render.glyph);
-                }
-            }
+            let mut runwriter = self.ecs.write_resource::<RunState>();
+            *runwriter = RunState::AwaitingInput;
         }
 
-        draw_ui(&self.ecs, ctx);
+        if newrunstate == RunState::ShowInventory {
+            let (result, item) = gui::show_inventory(self, ctx);
+            let next_state = match (result, item) {
+                (gui::ItemMenuResult::Cancel, _) => RunState::AwaitingInput,
+                (gui::ItemMenuResult::Selected, Some(item)) => {
+                    let player_entity = *self.ecs.fetch::<Entity>();
+                    self.ecs
+                        .write_storage::<WantsToUseItem>()
+                        .insert(player_entity, WantsToUseItem { item })
+                        .expect("Unable to insert intent");
+                    RunState::PlayerTurn
+                }
+                _ => newrunstate,
+            };
+            let mut runwriter = self.ecs.write_resource::<RunState>();
+            *runwriter = next_state;
+        }
 
-        match newrunstate {
-            RunState::ShowInventory => {
-                let result = gui::show_inventory(self, ctx);
-                if result == gui::ItemMenuResult::Cancel {
-                    let mut runwriter = self.ecs.write_resource::<RunState>();
-                    *runwriter = RunState::AwaitingInput;
+        if let RunState::ShowVendor { vendor, mode } = newrunstate {
+            let (result, item) = gui::show_vendor(self, ctx, vendor, mode);
+            let next_state = match (result, item) {
+                (gui::VendorResult::Cancel, _) => RunState::AwaitingInput,
+                (gui::VendorResult::SwitchMode, _) => RunState::ShowVendor {
+                    vendor,
+                    mode: match mode {
+                        gui::VendorMode::Buy => gui::VendorMode::Sell,
+                        gui::VendorMode::Sell => gui::VendorMode::Buy,
+                    },
+                },
+                (gui::VendorResult::Buy, Some(item)) => {
+                    trade::buy_item(&mut self.ecs, item);
+                    newrunstate
                 }
-            }
-            _ => {}
+                (gui::VendorResult::Sell, Some(item)) => {
+                    trade::sell_item(&mut self.ecs, vendor, item);
+                    newrunstate
+                }
+                _ => newrunstate,
+            };
+            let mut runwriter = self.ecs.write_resource::<RunState>();
+            *runwriter = next_state;
         }
 
         damage_system::delete_the_dead(&mut self.ecs);
@@ -145,13 +398,41 @@ impl GameState for State {
 }
 
 fn main() -> rltk::BError {
+    // Let a map builder be chosen from the command line, e.g. `--map=cellular`, and
+    // `--show-mapgen` replay how the map was built before the game starts
+    let mut map_builder: Option<String> = None;
+    let mut show_mapgen = false;
+    for arg in std::env::args().skip(1) {
+        if arg == "--show-mapgen" {
+            show_mapgen = true;
+        } else if let Some(name) = arg.strip_prefix("--map=") {
+            if !map_builders::BUILDER_NAMES.contains(&name) {
+                return Err(format!(
+                    "Unknown map builder '{}', expected one of: {}",
+                    name,
+                    map_builders::BUILDER_NAMES.join(", ")
+                )
+                .into());
+            }
+            map_builder = Some(name.to_string());
+        }
+    }
+
     // Set up initial game context
     use rltk::RltkBuilder;
     let mut context = RltkBuilder::simple80x50().with_title("Roguelike").build()?;
     context.with_post_scanlines(true);
 
     // Create game state and register ECS components
-    let mut gs = State { ecs: World::new() };
+    let mut gs = State {
+        ecs: World::new(),
+        map_builder,
+        show_mapgen,
+        mapgen_next_state: RunState::PreRun,
+        mapgen_history: Vec::new(),
+        mapgen_index: 0,
+        mapgen_timer: 0.0,
+    };
     gs.ecs.register::<Position>();
     gs.ecs.register::<Renderable>();
     gs.ecs.register::<Player>();
@@ -159,6 +440,8 @@ fn main() -> rltk::BError {
     gs.ecs.register::<Name>();
     gs.ecs.register::<Viewshed>();
     gs.ecs.register::<BlocksTile>();
+    gs.ecs.register::<BlocksVisibility>();
+    gs.ecs.register::<Door>();
     gs.ecs.register::<CombatStats>();
     gs.ecs.register::<WantsToMelee>();
     gs.ecs.register::<SufferDamage>();
@@ -166,25 +449,41 @@ fn main() -> rltk::BError {
     gs.ecs.register::<HealingPotion>();
     gs.ecs.register::<InBackpack>();
     gs.ecs.register::<WantsToPickUpItem>();
-
-    let map: Map = Map::new_map_rooms_and_corridors();
-    let (player_x, player_y) = map.rooms[0].center();
-    gs.ecs.insert(Point::new(player_x, player_y));
-    gs.ecs.insert(RunState::PreRun);
-    gs.ecs.insert(gamelog::GameLog {
-        entries: vec!["Welcome to Jaxon's Roguelike!".to_string()],
-    });
+    gs.ecs.register::<Hidden>();
+    gs.ecs.register::<EntryTrigger>();
+    gs.ecs.register::<SingleActivation>();
+    gs.ecs.register::<EntityMoved>();
+    gs.ecs.register::<InflictsDamage>();
+    gs.ecs.register::<TeleportsTarget>();
+    gs.ecs.register::<Vendor>();
+    gs.ecs.register::<Wallet>();
+    gs.ecs.register::<ItemValue>();
+    gs.ecs.register::<GoldPile>();
+    gs.ecs.register::<Corpse>();
+    gs.ecs.register::<WantsToUseItem>();
+    gs.ecs.register::<HungerClock>();
+    gs.ecs.register::<ProvidesFood>();
+    gs.ecs.register::<MagicMapper>();
+    gs.ecs.register::<DetectsMonsters>();
+    gs.ecs.register::<Telepathy>();
+    gs.ecs.register::<Description>();
+
+    gs.ecs.insert(Point::new(0, 0));
+    gs.ecs.insert(RunState::MapGeneration);
+    let mut gamelog = gamelog::GameLog::default();
+    gamelog.add("Welcome to Jaxon's Roguelike!");
+    gs.ecs.insert(gamelog);
+    gs.ecs.insert(TurnCounter::default());
+    gs.ecs.insert(autopilot::Autopilot::default());
     gs.ecs.insert(rltk::RandomNumberGenerator::new());
 
-    let player_entity = spawner::player(&mut gs.ecs, player_x, player_y);
-
-    for room in map.rooms.iter().skip(1) {
-        spawner::spawn_room(&mut gs.ecs, room);
-    }
-
-    gs.ecs.insert(map);
+    let player_entity = spawner::player(&mut gs.ecs, 0, 0);
     gs.ecs.insert(player_entity);
 
+    // Start in the town, unless a particular dungeon map was asked for
+    let first_depth = if gs.map_builder.is_some() { 1 } else { 0 };
+    gs.generate_world_map(first_depth);
+
     // Run the game's main loop
     rltk::main_loop(context, gs)
 }
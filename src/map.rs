@@ -1,60 +1,115 @@
-use super::Rect;
-use rltk::{Algorithm2D, BaseMap, Point, RGB, RandomNumberGenerator, Rltk};
+use rltk::{Algorithm2D, BaseMap, Point};
 use specs::prelude::*;
-use std::cmp::{max, min};
+use std::collections::HashSet;
 
-pub const MAPWIDTH: usize = 80;
-pub const MAPHEIGHT: usize = 43;
-pub const MAPCOUNT: usize = MAPHEIGHT * MAPWIDTH;
-
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Eq, Hash, Copy, Clone)]
 pub enum TileType {
     Wall,
     Floor,
+    DownStairs,
+    ShallowWater,
+    DeepWater,
+    Lava,
+    Chasm,
+    Bridge,
+    Grass,
+}
+
+impl TileType {
+    /// Whether something can stand on the tile.
+    pub fn is_walkable(self) -> bool {
+        match self {
+            TileType::Floor
+            | TileType::DownStairs
+            | TileType::ShallowWater
+            | TileType::Lava
+            | TileType::Bridge
+            | TileType::Grass => true,
+            TileType::Wall | TileType::DeepWater | TileType::Chasm => false,
+        }
+    }
+
+    /// Whether the tile blocks line of sight.
+    pub fn is_opaque(self) -> bool {
+        self == TileType::Wall
+    }
+
+    /// How many times longer than usual it takes to walk onto the tile, so that
+    /// pathfinding prefers to go around.
+    pub fn movement_cost(self) -> f32 {
+        match self {
+            TileType::ShallowWater => 2.0,
+            TileType::Lava => 10.0,
+            _ => 1.0,
+        }
+    }
+
+    /// What the tile is, as shown when the player looks at it.
+    pub fn description(self) -> &'static str {
+        match self {
+            TileType::Wall => "A solid stone wall.",
+            TileType::Floor => "Bare floor.",
+            TileType::DownStairs => "Stairs leading further down.",
+            TileType::ShallowWater => "Shallow water. Wading through it is slow going.",
+            TileType::DeepWater => "Deep water, too deep to cross.",
+            TileType::Lava => "Molten rock. It burns anything that steps in it.",
+            TileType::Chasm => "A chasm with no bottom in sight.",
+            TileType::Bridge => "A wooden bridge over the chasm.",
+            TileType::Grass => "Grass.",
+        }
+    }
+
+    /// The damage done each turn to anything standing on the tile.
+    pub fn damage_per_turn(self) -> i32 {
+        match self {
+            TileType::Lava => 5,
+            _ => 0,
+        }
+    }
+
+    /// How the log describes something being hurt by standing on the tile, following
+    /// its name.
+    pub fn damage_message(self) -> &'static str {
+        match self {
+            TileType::Lava => "is burned by the lava!",
+            _ => "is hurt!",
+        }
+    }
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Map {
     pub tiles: Vec<TileType>,
-    pub rooms: Vec<Rect>,
     pub width: i32,
     pub height: i32,
     pub revealed_tiles: Vec<bool>,
     pub visible_tiles: Vec<bool>,
     pub blocked: Vec<bool>,
+    pub view_blocked: HashSet<usize>,
     pub tile_content: Vec<Vec<Entity>>,
+    /// How far below the town this level is; the town itself is depth 0.
+    pub depth: i32,
 }
 
 impl Map {
-    pub fn xy_idx(&self, x: i32, y: i32) -> usize {
-        (y as usize * self.width as usize) + x as usize
-    }
-
-    fn apply_room_to_map(&mut self, room: &Rect) {
-        for y in room.y1 + 1..=room.y2 {
-            for x in room.x1 + 1..=room.x2 {
-                let idx = self.xy_idx(x, y);
-                self.tiles[idx] = TileType::Floor;
-            }
-        }
-    }
-
-    fn apply_horizontal_tunnel(&mut self, x1: i32, x2: i32, y: i32) {
-        for x in min(x1, x2)..=max(x1, x2) {
-            let idx = self.xy_idx(x, y);
-            if idx > 0 && idx < self.width as usize * self.height as usize {
-                self.tiles[idx as usize] = TileType::Floor;
-            }
+    /// Makes a `width` x `height` map of solid rock, ready for a map builder to carve into.
+    pub fn new(width: i32, height: i32) -> Map {
+        let map_tile_count = (width * height) as usize;
+        Map {
+            tiles: vec![TileType::Wall; map_tile_count],
+            width,
+            height,
+            revealed_tiles: vec![false; map_tile_count],
+            visible_tiles: vec![false; map_tile_count],
+            blocked: vec![false; map_tile_count],
+            view_blocked: HashSet::new(),
+            tile_content: vec![Vec::new(); map_tile_count],
+            depth: 0,
         }
     }
 
-    fn apply_vertical_tunnel(&mut self, y1: i32, y2: i32, x: i32) {
-        for y in min(y1, y2)..=max(y1, y2) {
-            let idx = self.xy_idx(x, y);
-            if idx > 0 && idx < self.width as usize * self.height as usize {
-                self.tiles[idx as usize] = TileType::Floor;
-            }
-        }
+    pub fn xy_idx(&self, x: i32, y: i32) -> usize {
+        (y as usize * self.width as usize) + x as usize
     }
 
     fn is_exit_valid(&self, x: i32, y: i32) -> bool {
@@ -67,7 +122,7 @@ impl Map {
 
     pub fn populate_blocked(&mut self) {
         for (i, tile) in self.tiles.iter_mut().enumerate() {
-            self.blocked[i] = *tile == TileType::Wall;
+            self.blocked[i] = !tile.is_walkable();
         }
     }
 
@@ -76,65 +131,11 @@ impl Map {
             content.clear();
         }
     }
-
-    /// Makes a new map using the algorithm from http://rogueliketutorials.com/tutorials/tcod/part-3/
-    /// This gives a handful of random rooms and corridors joining them together.
-    pub fn new_map_rooms_and_corridors() -> Map {
-        let mut map = Map {
-            tiles: vec![TileType::Wall; MAPCOUNT],
-            rooms: Vec::new(),
-            width: MAPWIDTH as i32,
-            height: MAPHEIGHT as i32,
-            revealed_tiles: vec![false; MAPCOUNT],
-            visible_tiles: vec![false; MAPCOUNT],
-            blocked: vec![false; MAPCOUNT],
-            tile_content: vec![Vec::new(); MAPCOUNT],
-        };
-
-        const MAX_ROOMS: i32 = 30;
-        const MIN_SIZE: i32 = 6;
-        const MAX_SIZE: i32 = 10;
-
-        let mut rng = RandomNumberGenerator::new();
-
-        for _i in 0..MAX_ROOMS {
-            let w = rng.range(MIN_SIZE, MAX_SIZE);
-            let h = rng.range(MIN_SIZE, MAX_SIZE);
-            let x = rng.roll_dice(1, map.width - w - 1) - 1;
-            let y = rng.roll_dice(1, map.height - h - 1) - 1;
-            let new_room = Rect::new(x, y, w, h);
-            let mut ok = true;
-            for other_room in map.rooms.iter() {
-                if new_room.intersect(other_room) {
-                    ok = false
-                }
-            }
-            if ok {
-                map.apply_room_to_map(&new_room);
-
-                if !map.rooms.is_empty() {
-                    let (new_x, new_y) = new_room.center();
-                    let (prev_x, prev_y) = map.rooms[map.rooms.len() - 1].center();
-                    if rng.range(0, 2) == 1 {
-                        map.apply_horizontal_tunnel(prev_x, new_x, prev_y);
-                        map.apply_vertical_tunnel(prev_y, new_y, new_x);
-                    } else {
-                        map.apply_vertical_tunnel(prev_y, new_y, prev_x);
-                        map.apply_horizontal_tunnel(prev_x, new_x, new_y);
-                    }
-                }
-
-                map.rooms.push(new_room);
-            }
-        }
-
-        map
-    }
 }
 
 impl BaseMap for Map {
     fn is_opaque(&self, idx: usize) -> bool {
-        self.tiles[idx] == TileType::Wall
+        self.tiles[idx].is_opaque() || self.view_blocked.contains(&idx)
     }
 
     fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
@@ -148,48 +149,23 @@ impl BaseMap for Map {
         let mut exits = rltk::SmallVec::new();
         let x = idx as i32 % self.width;
         let y = idx as i32 / self.width;
-        let w = self.width as usize;
-
-        // Cardinal directions
-        if self.is_exit_valid(x - 1, y) {
-            exits.push((idx - 1, 1.0))
-        };
-        if self.is_exit_valid(x + 1, y) {
-            exits.push((idx + 1, 1.0))
-        };
-        if self.is_exit_valid(x, y - 1) {
-            exits.push((idx - w, 1.0))
-        };
-        if self.is_exit_valid(x, y + 1) {
-            exits.push((idx + w, 1.0))
-        };
-
-        // Diagonals
-        if self.is_exit_valid(x - 1, y - 1) {
-            exits.push(((idx - w) - 1, 1.45));
-        }
-        if self.is_exit_valid(x + 1, y - 1) {
-            exits.push(((idx - w) + 1, 1.45));
-        }
-        if self.is_exit_valid(x - 1, y + 1) {
-            exits.push(((idx + w) - 1, 1.45));
-        }
-        if self.is_exit_valid(x + 1, y + 1) {
-            exits.push(((idx + w) + 1, 1.45));
-        }
 
-        // Diagonals
-        if self.is_exit_valid(x - 1, y - 1) {
-            exits.push(((idx - w) - 1, 1.45));
-        }
-        if self.is_exit_valid(x + 1, y - 1) {
-            exits.push(((idx - w) + 1, 1.45))
-        }
-        if self.is_exit_valid(x - 1, y + 1) {
-            exits.push(((idx + w) - 1, 1.45));
-        }
-        if self.is_exit_valid(x + 1, y + 1) {
-            exits.push(((idx + w) + 1, 1.45));
+        // Cardinal directions, then diagonals, weighted by how hard the tile is to cross
+        let steps = [
+            (-1, 0, 1.0),
+            (1, 0, 1.0),
+            (0, -1, 1.0),
+            (0, 1, 1.0),
+            (-1, -1, 1.45),
+            (1, -1, 1.45),
+            (-1, 1, 1.45),
+            (1, 1, 1.45),
+        ];
+        for (delta_x, delta_y, distance) in steps {
+            if self.is_exit_valid(x + delta_x, y + delta_y) {
+                let exit_idx = self.xy_idx(x + delta_x, y + delta_y);
+                exits.push((exit_idx, distance * self.tiles[exit_idx].movement_cost()));
+            }
         }
 
         exits
@@ -201,39 +177,3 @@ impl Algorithm2D for Map {
         Point::new(self.width, self.height)
     }
 }
-
-pub fn draw_map(ecs: &World, ctx: &mut Rltk) {
-    let map = ecs.fetch::<Map>();
-
-    let mut y = 0;
-    let mut x = 0;
-    for (idx, tile) in map.tiles.iter().enumerate() {
-        // Render a tile depending upon the tile type
-
-        if map.revealed_tiles[idx] {
-            let glyph;
-            let mut fg;
-            match tile {
-                TileType::Floor => {
-                    glyph = rltk::to_cp437('.');
-                    fg = RGB::from_f32(0.0, 0.5, 0.5);
-                }
-                TileType::Wall => {
-                    glyph = rltk::to_cp437('#');
-                    fg = RGB::from_f32(0., 1.0, 0.);
-                }
-            }
-            if !map.visible_tiles[idx] {
-                fg = fg.to_greyscale()
-            }
-            ctx.set(x, y, fg, RGB::from_f32(0., 0., 0.), glyph);
-        }
-
-        // Move the coordinates
-        x += 1;
-        if x > MAPWIDTH as i32 - 1 {
-            x = 0;
-            y += 1;
-        }
-    }
-}
@@ -0,0 +1,140 @@
+use super::{
+    Map, MapBuilder, Position, Rect, SnapshotHistory, TileType, apply_horizontal_tunnel,
+    apply_room_to_map, apply_vertical_tunnel, spawn_rooms,
+};
+use rltk::RandomNumberGenerator;
+use specs::prelude::*;
+
+/// Makes a new map by binary space partitioning: the whole area is recursively
+/// split into smaller leaves, each leaf gets a room, and sibling leaves are joined
+/// by a corridor so that every room is reachable.
+pub struct BspDungeonBuilder {
+    map: Map,
+    starting_position: Position,
+    rooms: Vec<Rect>,
+    history: SnapshotHistory,
+}
+
+impl MapBuilder for BspDungeonBuilder {
+    fn build_map(&mut self) {
+        let mut rng = RandomNumberGenerator::new();
+
+        let area = Rect::new(0, 0, self.map.width - 1, self.map.height - 1);
+        self.subdivide(&area, &mut rng);
+
+        let (start_x, start_y) = self.rooms[0].center();
+        self.starting_position = Position {
+            x: start_x,
+            y: start_y,
+        };
+
+        // The way down is in the last room carved
+        let (stairs_x, stairs_y) = self.rooms[self.rooms.len() - 1].center();
+        let stairs_idx = self.map.xy_idx(stairs_x, stairs_y);
+        self.map.tiles[stairs_idx] = TileType::DownStairs;
+        self.take_snapshot();
+    }
+
+    fn spawn_entities(&mut self, ecs: &mut World) {
+        spawn_rooms(ecs, &self.map, &self.rooms);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn history(&self) -> &SnapshotHistory {
+        &self.history
+    }
+
+    fn history_mut(&mut self) -> &mut SnapshotHistory {
+        &mut self.history
+    }
+
+    fn get_rooms(&self) -> Vec<Rect> {
+        self.rooms.clone()
+    }
+}
+
+impl BspDungeonBuilder {
+    pub fn new(width: i32, height: i32) -> BspDungeonBuilder {
+        BspDungeonBuilder {
+            map: Map::new(width, height),
+            starting_position: Position { x: 0, y: 0 },
+            rooms: Vec::new(),
+            history: SnapshotHistory::default(),
+        }
+    }
+
+    /// Splits `area` in two (or carves a room if it's too small to split) and returns
+    /// the index of one of the rooms within it, so the caller can connect to it.
+    fn subdivide(&mut self, area: &Rect, rng: &mut RandomNumberGenerator) -> usize {
+        const MIN_LEAF_SIZE: i32 = 10;
+
+        let width = area.x2 - area.x1;
+        let height = area.y2 - area.y1;
+        let can_split_x = width >= MIN_LEAF_SIZE * 2;
+        let can_split_y = height >= MIN_LEAF_SIZE * 2;
+
+        let split_x = match (can_split_x, can_split_y) {
+            (false, false) => return self.place_room(area, rng),
+            (true, true) => rng.range(0, 2) == 0,
+            (can_split_x, _) => can_split_x,
+        };
+
+        let (first, second) = if split_x {
+            let x = rng.range(area.x1 + MIN_LEAF_SIZE, area.x2 - MIN_LEAF_SIZE + 1);
+            (
+                Rect::new(area.x1, area.y1, x - area.x1, height),
+                Rect::new(x, area.y1, area.x2 - x, height),
+            )
+        } else {
+            let y = rng.range(area.y1 + MIN_LEAF_SIZE, area.y2 - MIN_LEAF_SIZE + 1);
+            (
+                Rect::new(area.x1, area.y1, width, y - area.y1),
+                Rect::new(area.x1, y, width, area.y2 - y),
+            )
+        };
+
+        let first_room = self.subdivide(&first, rng);
+        let second_room = self.subdivide(&second, rng);
+
+        let (x1, y1) = self.rooms[first_room].center();
+        let (x2, y2) = self.rooms[second_room].center();
+        if rng.range(0, 2) == 1 {
+            apply_horizontal_tunnel(&mut self.map, x1, x2, y1);
+            apply_vertical_tunnel(&mut self.map, y1, y2, x2);
+        } else {
+            apply_vertical_tunnel(&mut self.map, y1, y2, x1);
+            apply_horizontal_tunnel(&mut self.map, x1, x2, y2);
+        }
+        self.take_snapshot();
+
+        if rng.range(0, 2) == 1 {
+            first_room
+        } else {
+            second_room
+        }
+    }
+
+    /// Carves a randomly sized room that fits inside a BSP leaf, walls included.
+    fn place_room(&mut self, leaf: &Rect, rng: &mut RandomNumberGenerator) -> usize {
+        const MIN_SIZE: i32 = 4;
+        const MAX_SIZE: i32 = 10;
+
+        let w = rng.range(MIN_SIZE, i32::min(MAX_SIZE, leaf.x2 - leaf.x1));
+        let h = rng.range(MIN_SIZE, i32::min(MAX_SIZE, leaf.y2 - leaf.y1));
+        let x = rng.range(leaf.x1, leaf.x2 - w);
+        let y = rng.range(leaf.y1, leaf.y2 - h);
+
+        let room = Rect::new(x, y, w, h);
+        apply_room_to_map(&mut self.map, &room);
+        self.rooms.push(room);
+        self.take_snapshot();
+        self.rooms.len() - 1
+    }
+}
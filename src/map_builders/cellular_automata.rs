@@ -0,0 +1,121 @@
+use super::{
+    Map, MapBuilder, Position, SnapshotHistory, TileType, cull_unreachable_areas,
+    spawn_voronoi_regions,
+};
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+/// Makes a new cave level with cellular automata: random noise is smoothed into
+/// open caverns, anything unreachable from the start is filled in, and the down
+/// stairs go on the furthest reachable tile.
+pub struct CellularAutomataBuilder {
+    map: Map,
+    starting_position: Position,
+    history: SnapshotHistory,
+}
+
+impl MapBuilder for CellularAutomataBuilder {
+    fn build_map(&mut self) {
+        const SMOOTHING_ITERATIONS: i32 = 15;
+
+        let mut rng = RandomNumberGenerator::new();
+
+        // Seed the map with random noise, leaving the outer edge solid
+        for y in 1..self.map.height - 1 {
+            for x in 1..self.map.width - 1 {
+                let idx = self.map.xy_idx(x, y);
+                if rng.roll_dice(1, 100) > 55 {
+                    self.map.tiles[idx] = TileType::Floor;
+                }
+            }
+        }
+        self.take_snapshot();
+
+        // Anything crowded by walls (or completely isolated) becomes a wall
+        for _i in 0..SMOOTHING_ITERATIONS {
+            let mut new_tiles = self.map.tiles.clone();
+            for y in 1..self.map.height - 1 {
+                for x in 1..self.map.width - 1 {
+                    let mut neighbors = 0;
+                    for (dx, dy) in [
+                        (-1, -1),
+                        (0, -1),
+                        (1, -1),
+                        (-1, 0),
+                        (1, 0),
+                        (-1, 1),
+                        (0, 1),
+                        (1, 1),
+                    ] {
+                        if self.map.tiles[self.map.xy_idx(x + dx, y + dy)] == TileType::Wall {
+                            neighbors += 1;
+                        }
+                    }
+
+                    let idx = self.map.xy_idx(x, y);
+                    new_tiles[idx] = if neighbors > 4 || neighbors == 0 {
+                        TileType::Wall
+                    } else {
+                        TileType::Floor
+                    };
+                }
+            }
+            self.map.tiles = new_tiles;
+            self.take_snapshot();
+        }
+
+        // Start on the floor tile closest to the middle of the map
+        let center = Point::new(self.map.width / 2, self.map.height / 2);
+        let start_idx = self
+            .map
+            .tiles
+            .iter()
+            .enumerate()
+            .filter(|(_idx, tile)| **tile == TileType::Floor)
+            .map(|(idx, _tile)| idx)
+            .min_by_key(|idx| {
+                let x = *idx as i32 % self.map.width;
+                let y = *idx as i32 / self.map.width;
+                (x - center.x).pow(2) + (y - center.y).pow(2)
+            })
+            .expect("Cave generation produced no floor");
+        self.starting_position = Position {
+            x: start_idx as i32 % self.map.width,
+            y: start_idx as i32 / self.map.width,
+        };
+
+        let exit_idx = cull_unreachable_areas(&mut self.map, start_idx);
+        self.map.tiles[exit_idx] = TileType::DownStairs;
+        self.take_snapshot();
+    }
+
+    fn spawn_entities(&mut self, ecs: &mut World) {
+        spawn_voronoi_regions(ecs, &self.map, &self.starting_position);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn history(&self) -> &SnapshotHistory {
+        &self.history
+    }
+
+    fn history_mut(&mut self) -> &mut SnapshotHistory {
+        &mut self.history
+    }
+}
+
+impl CellularAutomataBuilder {
+    pub fn new(width: i32, height: i32) -> CellularAutomataBuilder {
+        CellularAutomataBuilder {
+            map: Map::new(width, height),
+            starting_position: Position { x: 0, y: 0 },
+            history: SnapshotHistory::default(),
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use super::{Map, Position, Rect, TileType, spawner};
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+use std::cmp::{max, min};
+use std::collections::HashMap;
+
+pub fn apply_room_to_map(map: &mut Map, room: &Rect) {
+    for y in room.y1 + 1..=room.y2 {
+        for x in room.x1 + 1..=room.x2 {
+            let idx = map.xy_idx(x, y);
+            map.tiles[idx] = TileType::Floor;
+        }
+    }
+}
+
+pub fn apply_horizontal_tunnel(map: &mut Map, x1: i32, x2: i32, y: i32) {
+    for x in min(x1, x2)..=max(x1, x2) {
+        let idx = map.xy_idx(x, y);
+        if idx > 0 && idx < map.width as usize * map.height as usize {
+            map.tiles[idx] = TileType::Floor;
+        }
+    }
+}
+
+pub fn apply_vertical_tunnel(map: &mut Map, y1: i32, y2: i32, x: i32) {
+    for y in min(y1, y2)..=max(y1, y2) {
+        let idx = map.xy_idx(x, y);
+        if idx > 0 && idx < map.width as usize * map.height as usize {
+            map.tiles[idx] = TileType::Floor;
+        }
+    }
+}
+
+/// A door fits on a floor tile that forms a one-tile-wide passage: walls on two
+/// opposite sides and floor on the other two.
+fn door_possible(map: &Map, x: i32, y: i32) -> bool {
+    if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 {
+        return false;
+    }
+    let is_floor = |x: i32, y: i32| map.tiles[map.xy_idx(x, y)] == TileType::Floor;
+
+    if !is_floor(x, y) {
+        return false;
+    }
+    let horizontal = is_floor(x - 1, y) && is_floor(x + 1, y);
+    let vertical = is_floor(x, y - 1) && is_floor(x, y + 1);
+    (horizontal && !is_floor(x, y - 1) && !is_floor(x, y + 1))
+        || (vertical && !is_floor(x - 1, y) && !is_floor(x + 1, y))
+}
+
+/// Returns the tiles where a corridor breaks through the wall surrounding a room.
+pub fn door_locations(map: &Map, rooms: &[Rect]) -> Vec<usize> {
+    let mut doors: Vec<usize> = Vec::new();
+
+    for room in rooms.iter() {
+        let mut perimeter: Vec<(i32, i32)> = Vec::new();
+        for x in room.x1 + 1..=room.x2 {
+            perimeter.push((x, room.y1));
+            perimeter.push((x, room.y2 + 1));
+        }
+        for y in room.y1 + 1..=room.y2 {
+            perimeter.push((room.x1, y));
+            perimeter.push((room.x2 + 1, y));
+        }
+
+        for (x, y) in perimeter {
+            let idx = map.xy_idx(x, y);
+            if door_possible(map, x, y) && !doors.contains(&idx) {
+                doors.push(idx);
+            }
+        }
+    }
+
+    doors
+}
+
+/// Walls off every tile that can't be reached from `start_idx`, returning the
+/// index of the reachable tile that is furthest away from it.
+pub fn cull_unreachable_areas(map: &mut Map, start_idx: usize) -> usize {
+    map.populate_blocked();
+    let dijkstra_map = rltk::DijkstraMap::new(
+        map.width as usize,
+        map.height as usize,
+        &[start_idx],
+        map,
+        f32::MAX,
+    );
+
+    let mut exit_idx = start_idx;
+    let mut exit_distance = 0.0f32;
+    for (idx, tile) in map.tiles.iter_mut().enumerate() {
+        if *tile != TileType::Floor {
+            continue;
+        }
+        let distance = dijkstra_map.map[idx];
+        if distance == f32::MAX {
+            *tile = TileType::Wall;
+        } else if distance > exit_distance {
+            exit_idx = idx;
+            exit_distance = distance;
+        }
+    }
+
+    map.populate_blocked();
+    exit_idx
+}
+
+/// Takes one step in a random cardinal direction, staying clear of the map's edge.
+pub fn random_step(map: &Map, rng: &mut RandomNumberGenerator, x: i32, y: i32) -> (i32, i32) {
+    match rng.roll_dice(1, 4) {
+        1 if x > 2 => (x - 1, y),
+        2 if x < map.width - 2 => (x + 1, y),
+        3 if y > 2 => (x, y - 1),
+        4 if y < map.height - 2 => (x, y + 1),
+        _ => (x, y),
+    }
+}
+
+/// Groups the map's floor into regions around randomly placed seed points (a
+/// Voronoi diagram), for spawning on maps that don't have rooms. Bigger maps get
+/// more regions, about one for every `TILES_PER_REGION` tiles.
+fn voronoi_regions(map: &Map, rng: &mut RandomNumberGenerator) -> Vec<Vec<usize>> {
+    const TILES_PER_REGION: usize = 170;
+
+    let region_seeds = (map.tiles.len() / TILES_PER_REGION).max(1);
+    let mut seeds: Vec<Point> = Vec::new();
+    while seeds.len() < region_seeds {
+        let point = Point::new(
+            rng.roll_dice(1, map.width - 2),
+            rng.roll_dice(1, map.height - 2),
+        );
+        if !seeds.contains(&point) {
+            seeds.push(point);
+        }
+    }
+
+    let mut regions: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (idx, tile) in map.tiles.iter().enumerate() {
+        if *tile != TileType::Floor {
+            continue;
+        }
+        let point = Point::new(idx as i32 % map.width, idx as i32 / map.width);
+        let closest_seed = seeds
+            .iter()
+            .enumerate()
+            .map(|(seed_idx, seed)| {
+                let distance = rltk::DistanceAlg::PythagorasSquared.distance2d(point, *seed);
+                (seed_idx, distance)
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(seed_idx, _distance)| seed_idx)
+            .unwrap();
+        regions.entry(closest_seed).or_default().push(idx);
+    }
+
+    regions.into_values().collect()
+}
+
+/// Spawns doors, then fills every room but the first (where the player starts).
+pub fn spawn_rooms(ecs: &mut World, map: &Map, rooms: &[Rect]) {
+    for idx in door_locations(map, rooms) {
+        let x = idx as i32 % map.width;
+        let y = idx as i32 / map.width;
+        spawner::door(ecs, x, y);
+    }
+
+    for room in rooms.iter().skip(1) {
+        spawner::spawn_room(ecs, room);
+    }
+}
+
+/// Fills a room-less map by spawning into each of its Voronoi regions, keeping the
+/// player's starting tile clear.
+pub fn spawn_voronoi_regions(ecs: &mut World, map: &Map, starting_position: &Position) {
+    let regions = {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        voronoi_regions(map, &mut rng)
+    };
+
+    let start_idx = map.xy_idx(starting_position.x, starting_position.y);
+    for mut region in regions {
+        region.retain(|idx| *idx != start_idx);
+        spawner::spawn_region(ecs, &region);
+    }
+}
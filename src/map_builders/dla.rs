@@ -0,0 +1,205 @@
+use super::{
+    Map, MapBuilder, Position, SnapshotHistory, TileType, cull_unreachable_areas, random_step,
+    spawn_voronoi_regions,
+};
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum DlaAlgorithm {
+    WalkInwards,
+    WalkOutwards,
+    CentralAttractor,
+}
+
+pub struct DlaSettings {
+    pub algorithm: DlaAlgorithm,
+    pub brush_size: i32,
+    pub floor_percent: f32,
+}
+
+/// Makes a new map by diffusion-limited aggregation: particles wander around until
+/// they bump into the growing seed in the middle of the map, and stick to it.
+pub struct DlaBuilder {
+    map: Map,
+    starting_position: Position,
+    history: SnapshotHistory,
+    settings: DlaSettings,
+}
+
+impl MapBuilder for DlaBuilder {
+    fn build_map(&mut self) {
+        let mut rng = RandomNumberGenerator::new();
+
+        // Carve a small seed for everything else to stick to
+        self.starting_position = Position {
+            x: self.map.width / 2,
+            y: self.map.height / 2,
+        };
+        let (start_x, start_y) = (self.starting_position.x, self.starting_position.y);
+        for (x, y) in [
+            (start_x, start_y),
+            (start_x - 1, start_y),
+            (start_x + 1, start_y),
+            (start_x, start_y - 1),
+            (start_x, start_y + 1),
+        ] {
+            let idx = self.map.xy_idx(x, y);
+            self.map.tiles[idx] = TileType::Floor;
+        }
+        self.take_snapshot();
+
+        let total_tiles = self.map.width * self.map.height;
+        let desired_floor_tiles = (self.settings.floor_percent * total_tiles as f32) as usize;
+        let mut floor_tile_count = self.floor_tile_count();
+        let mut particle_count = 0;
+
+        while floor_tile_count < desired_floor_tiles {
+            match self.settings.algorithm {
+                DlaAlgorithm::WalkInwards => {
+                    // Wander in from a random point until the particle hits the floor,
+                    // then stick to where it was just before
+                    let mut digger_x = rng.roll_dice(1, self.map.width - 3) + 1;
+                    let mut digger_y = rng.roll_dice(1, self.map.height - 3) + 1;
+                    let (mut prev_x, mut prev_y) = (digger_x, digger_y);
+                    while self.map.tiles[self.map.xy_idx(digger_x, digger_y)] == TileType::Wall {
+                        (prev_x, prev_y) = (digger_x, digger_y);
+                        (digger_x, digger_y) = random_step(&self.map, &mut rng, digger_x, digger_y);
+                    }
+                    self.paint(prev_x, prev_y);
+                }
+                DlaAlgorithm::WalkOutwards => {
+                    // Wander out from the middle until the particle leaves the floor
+                    let (mut digger_x, mut digger_y) = (start_x, start_y);
+                    while self.map.tiles[self.map.xy_idx(digger_x, digger_y)] == TileType::Floor {
+                        (digger_x, digger_y) = random_step(&self.map, &mut rng, digger_x, digger_y);
+                    }
+                    self.paint(digger_x, digger_y);
+                }
+                DlaAlgorithm::CentralAttractor => {
+                    // Head in a straight line from a random point towards the middle
+                    let digger = Point::new(
+                        rng.roll_dice(1, self.map.width - 3) + 1,
+                        rng.roll_dice(1, self.map.height - 3) + 1,
+                    );
+                    let mut prev = digger;
+                    for step in rltk::line2d(
+                        rltk::LineAlg::Bresenham,
+                        digger,
+                        Point::new(start_x, start_y),
+                    ) {
+                        if self.map.tiles[self.map.xy_idx(step.x, step.y)] != TileType::Wall {
+                            break;
+                        }
+                        prev = step;
+                    }
+                    self.paint(prev.x, prev.y);
+                }
+            }
+
+            // Individual particles are too small to be worth a snapshot each
+            particle_count += 1;
+            if particle_count % 10 == 0 {
+                self.take_snapshot();
+            }
+            floor_tile_count = self.floor_tile_count();
+        }
+
+        let start_idx = self.map.xy_idx(start_x, start_y);
+        let exit_idx = cull_unreachable_areas(&mut self.map, start_idx);
+        self.map.tiles[exit_idx] = TileType::DownStairs;
+        self.take_snapshot();
+    }
+
+    fn spawn_entities(&mut self, ecs: &mut World) {
+        spawn_voronoi_regions(ecs, &self.map, &self.starting_position);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn history(&self) -> &SnapshotHistory {
+        &self.history
+    }
+
+    fn history_mut(&mut self) -> &mut SnapshotHistory {
+        &mut self.history
+    }
+}
+
+impl DlaBuilder {
+    pub fn new(width: i32, height: i32, settings: DlaSettings) -> DlaBuilder {
+        DlaBuilder {
+            map: Map::new(width, height),
+            starting_position: Position { x: 0, y: 0 },
+            history: SnapshotHistory::default(),
+            settings,
+        }
+    }
+
+    pub fn walk_inwards(width: i32, height: i32) -> DlaBuilder {
+        DlaBuilder::new(
+            width,
+            height,
+            DlaSettings {
+                algorithm: DlaAlgorithm::WalkInwards,
+                brush_size: 1,
+                floor_percent: 0.25,
+            },
+        )
+    }
+
+    pub fn walk_outwards(width: i32, height: i32) -> DlaBuilder {
+        DlaBuilder::new(
+            width,
+            height,
+            DlaSettings {
+                algorithm: DlaAlgorithm::WalkOutwards,
+                brush_size: 2,
+                floor_percent: 0.25,
+            },
+        )
+    }
+
+    pub fn central_attractor(width: i32, height: i32) -> DlaBuilder {
+        DlaBuilder::new(
+            width,
+            height,
+            DlaSettings {
+                algorithm: DlaAlgorithm::CentralAttractor,
+                brush_size: 2,
+                floor_percent: 0.25,
+            },
+        )
+    }
+
+    /// Turns the tiles under the brush into floor, keeping clear of the map's edge.
+    fn paint(&mut self, x: i32, y: i32) {
+        let half_brush = self.settings.brush_size / 2;
+        for brush_y in y - half_brush..y - half_brush + self.settings.brush_size {
+            for brush_x in x - half_brush..x - half_brush + self.settings.brush_size {
+                if brush_x > 1
+                    && brush_x < self.map.width - 1
+                    && brush_y > 1
+                    && brush_y < self.map.height - 1
+                {
+                    let idx = self.map.xy_idx(brush_x, brush_y);
+                    self.map.tiles[idx] = TileType::Floor;
+                }
+            }
+        }
+    }
+
+    fn floor_tile_count(&self) -> usize {
+        self.map
+            .tiles
+            .iter()
+            .filter(|tile| **tile == TileType::Floor)
+            .count()
+    }
+}
@@ -0,0 +1,159 @@
+use super::{
+    Map, MapBuilder, Position, SnapshotHistory, TileType, cull_unreachable_areas, random_step,
+    spawn_voronoi_regions,
+};
+use rltk::RandomNumberGenerator;
+use specs::prelude::*;
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum DrunkSpawnMode {
+    StartingPoint,
+    Random,
+}
+
+pub struct DrunkardSettings {
+    pub spawn_mode: DrunkSpawnMode,
+    pub drunken_lifetime: i32,
+    pub floor_percent: f32,
+}
+
+/// Makes a new map by letting "drunken" diggers stagger around the map, carving
+/// floor wherever they go, until enough of the map is open.
+pub struct DrunkardsWalkBuilder {
+    map: Map,
+    starting_position: Position,
+    history: SnapshotHistory,
+    settings: DrunkardSettings,
+}
+
+impl MapBuilder for DrunkardsWalkBuilder {
+    fn build_map(&mut self) {
+        let mut rng = RandomNumberGenerator::new();
+
+        // Start in the middle of the map
+        self.starting_position = Position {
+            x: self.map.width / 2,
+            y: self.map.height / 2,
+        };
+        let start_idx = self
+            .map
+            .xy_idx(self.starting_position.x, self.starting_position.y);
+        self.map.tiles[start_idx] = TileType::Floor;
+
+        let total_tiles = self.map.width * self.map.height;
+        let desired_floor_tiles = (self.settings.floor_percent * total_tiles as f32) as usize;
+        let mut floor_tile_count = self.floor_tile_count();
+        let mut digger_count = 0;
+
+        while floor_tile_count < desired_floor_tiles {
+            let mut did_something = false;
+            let (mut drunk_x, mut drunk_y) = match self.settings.spawn_mode {
+                DrunkSpawnMode::Random if digger_count > 0 => (
+                    rng.roll_dice(1, self.map.width - 3) + 1,
+                    rng.roll_dice(1, self.map.height - 3) + 1,
+                ),
+                _ => (self.starting_position.x, self.starting_position.y),
+            };
+
+            for _step in 0..self.settings.drunken_lifetime {
+                let drunk_idx = self.map.xy_idx(drunk_x, drunk_y);
+                if self.map.tiles[drunk_idx] == TileType::Wall {
+                    did_something = true;
+                }
+                self.map.tiles[drunk_idx] = TileType::Floor;
+
+                (drunk_x, drunk_y) = random_step(&self.map, &mut rng, drunk_x, drunk_y);
+            }
+
+            if did_something {
+                self.take_snapshot();
+            }
+
+            digger_count += 1;
+            floor_tile_count = self.floor_tile_count();
+        }
+
+        // Random diggers may have wandered off on their own, so tidy up behind them
+        let exit_idx = cull_unreachable_areas(&mut self.map, start_idx);
+        self.map.tiles[exit_idx] = TileType::DownStairs;
+        self.take_snapshot();
+    }
+
+    fn spawn_entities(&mut self, ecs: &mut World) {
+        spawn_voronoi_regions(ecs, &self.map, &self.starting_position);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn history(&self) -> &SnapshotHistory {
+        &self.history
+    }
+
+    fn history_mut(&mut self) -> &mut SnapshotHistory {
+        &mut self.history
+    }
+}
+
+impl DrunkardsWalkBuilder {
+    pub fn new(width: i32, height: i32, settings: DrunkardSettings) -> DrunkardsWalkBuilder {
+        DrunkardsWalkBuilder {
+            map: Map::new(width, height),
+            starting_position: Position { x: 0, y: 0 },
+            history: SnapshotHistory::default(),
+            settings,
+        }
+    }
+
+    /// Every digger starts from the middle, wandering a long way: one big cavern.
+    pub fn open_area(width: i32, height: i32) -> DrunkardsWalkBuilder {
+        DrunkardsWalkBuilder::new(
+            width,
+            height,
+            DrunkardSettings {
+                spawn_mode: DrunkSpawnMode::StartingPoint,
+                drunken_lifetime: 400,
+                floor_percent: 0.5,
+            },
+        )
+    }
+
+    /// Diggers start anywhere and wander a long way: broad, joined-up halls.
+    pub fn open_halls(width: i32, height: i32) -> DrunkardsWalkBuilder {
+        DrunkardsWalkBuilder::new(
+            width,
+            height,
+            DrunkardSettings {
+                spawn_mode: DrunkSpawnMode::Random,
+                drunken_lifetime: 400,
+                floor_percent: 0.5,
+            },
+        )
+    }
+
+    /// Diggers start anywhere but tire quickly: narrow, twisting passages.
+    pub fn winding_passages(width: i32, height: i32) -> DrunkardsWalkBuilder {
+        DrunkardsWalkBuilder::new(
+            width,
+            height,
+            DrunkardSettings {
+                spawn_mode: DrunkSpawnMode::Random,
+                drunken_lifetime: 100,
+                floor_percent: 0.4,
+            },
+        )
+    }
+
+    fn floor_tile_count(&self) -> usize {
+        self.map
+            .tiles
+            .iter()
+            .filter(|tile| **tile == TileType::Floor)
+            .count()
+    }
+}
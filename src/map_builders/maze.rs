@@ -0,0 +1,111 @@
+use super::{
+    Map, MapBuilder, Position, SnapshotHistory, TileType, cull_unreachable_areas,
+    spawn_voronoi_regions,
+};
+use rltk::RandomNumberGenerator;
+use specs::prelude::*;
+
+/// Makes a labyrinth with a recursive backtracker. The maze is generated on a grid
+/// of cells at half the map's resolution: each cell becomes a floor tile, and the
+/// tiles between cells are the walls that the backtracker knocks down.
+pub struct MazeBuilder {
+    map: Map,
+    starting_position: Position,
+    history: SnapshotHistory,
+}
+
+impl MapBuilder for MazeBuilder {
+    fn build_map(&mut self) {
+        const SNAPSHOT_EVERY: i32 = 10;
+
+        let mut rng = RandomNumberGenerator::new();
+        let cells_wide = (self.map.width - 2) / 2;
+        let cells_high = (self.map.height - 2) / 2;
+        let cell_idx = |x: i32, y: i32| (y * cells_wide + x) as usize;
+
+        let mut visited = vec![false; (cells_wide * cells_high) as usize];
+        let mut backtrace: Vec<(i32, i32)> = vec![(0, 0)];
+        visited[0] = true;
+        self.carve(0, 0, 0, 0);
+
+        let mut carved = 0;
+        while let Some(&(x, y)) = backtrace.last() {
+            let neighbors: Vec<(i32, i32)> = [(0, -1), (1, 0), (0, 1), (-1, 0)]
+                .into_iter()
+                .filter(|(dx, dy)| {
+                    let (nx, ny) = (x + dx, y + dy);
+                    nx >= 0
+                        && nx < cells_wide
+                        && ny >= 0
+                        && ny < cells_high
+                        && !visited[cell_idx(nx, ny)]
+                })
+                .collect();
+
+            if neighbors.is_empty() {
+                // Dead end, so go back and look for an unexplored branch
+                backtrace.pop();
+                continue;
+            }
+
+            let (dx, dy) = neighbors[(rng.roll_dice(1, neighbors.len() as i32) - 1) as usize];
+            self.carve(x + dx, y + dy, dx, dy);
+            visited[cell_idx(x + dx, y + dy)] = true;
+            backtrace.push((x + dx, y + dy));
+
+            carved += 1;
+            if carved % SNAPSHOT_EVERY == 0 {
+                self.take_snapshot();
+            }
+        }
+
+        // Start in the top-left corner and put the exit as far away as possible
+        self.starting_position = Position { x: 1, y: 1 };
+        let start_idx = self.map.xy_idx(1, 1);
+        let exit_idx = cull_unreachable_areas(&mut self.map, start_idx);
+        self.map.tiles[exit_idx] = TileType::DownStairs;
+        self.take_snapshot();
+    }
+
+    fn spawn_entities(&mut self, ecs: &mut World) {
+        spawn_voronoi_regions(ecs, &self.map, &self.starting_position);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn history(&self) -> &SnapshotHistory {
+        &self.history
+    }
+
+    fn history_mut(&mut self) -> &mut SnapshotHistory {
+        &mut self.history
+    }
+}
+
+impl MazeBuilder {
+    pub fn new(width: i32, height: i32) -> MazeBuilder {
+        MazeBuilder {
+            map: Map::new(width, height),
+            starting_position: Position { x: 0, y: 0 },
+            history: SnapshotHistory::default(),
+        }
+    }
+
+    /// Opens up the maze cell at (`x`, `y`) along with the wall between it and the
+    /// cell it was entered from, which lies in the opposite direction of (`dx`, `dy`).
+    fn carve(&mut self, x: i32, y: i32, dx: i32, dy: i32) {
+        let map_x = 1 + x * 2;
+        let map_y = 1 + y * 2;
+
+        let cell = self.map.xy_idx(map_x, map_y);
+        self.map.tiles[cell] = TileType::Floor;
+        let wall = self.map.xy_idx(map_x - dx, map_y - dy);
+        self.map.tiles[wall] = TileType::Floor;
+    }
+}
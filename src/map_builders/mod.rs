@@ -0,0 +1,240 @@
+use super::{Map, Position, Rect, TileType, spawner};
+use rltk::RandomNumberGenerator;
+use specs::prelude::*;
+
+mod common;
+use common::*;
+
+mod simple_map;
+use simple_map::SimpleMapBuilder;
+
+mod bsp_dungeon;
+use bsp_dungeon::BspDungeonBuilder;
+
+mod cellular_automata;
+use cellular_automata::CellularAutomataBuilder;
+
+mod drunkard;
+use drunkard::DrunkardsWalkBuilder;
+
+mod dla;
+use dla::DlaBuilder;
+
+mod maze;
+use maze::MazeBuilder;
+
+mod prefab_builder;
+use prefab_builder::PrefabBuilder;
+
+mod waveform_collapse;
+use waveform_collapse::WaveformCollapseBuilder;
+
+mod terrain;
+use terrain::TerrainBuilder;
+
+mod town;
+use town::TownBuilder;
+
+/// One step of a map's construction, as the map generation visualizer shows it.
+/// Only the tiles are kept, since that is all it draws.
+#[derive(Clone)]
+pub struct MapSnapshot {
+    pub width: i32,
+    pub height: i32,
+    pub tiles: Vec<TileType>,
+}
+
+/// The snapshots a builder has taken, oldest first. Nothing is kept unless
+/// `record` has been called, so levels built without the visualizer don't pay for
+/// copying the map at every step.
+#[derive(Default)]
+pub struct SnapshotHistory {
+    recording: bool,
+    snapshots: Vec<MapSnapshot>,
+}
+
+impl SnapshotHistory {
+    pub fn record(&mut self) {
+        self.recording = true;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    pub fn push(&mut self, map: &Map) {
+        if self.recording {
+            self.snapshots.push(MapSnapshot {
+                width: map.width,
+                height: map.height,
+                tiles: map.tiles.clone(),
+            });
+        }
+    }
+
+    pub fn snapshots(&self) -> &[MapSnapshot] {
+        &self.snapshots
+    }
+}
+
+/// A map generation algorithm. Builders carve out a `Map`, decide where the player
+/// starts, and populate the level once it has been handed to the ECS. Snapshots
+/// taken along the way can be replayed with `--show-mapgen`.
+pub trait MapBuilder {
+    fn build_map(&mut self);
+    fn spawn_entities(&mut self, ecs: &mut World);
+    fn get_map(&self) -> Map;
+    fn get_starting_position(&self) -> Position;
+    fn history(&self) -> &SnapshotHistory;
+    fn history_mut(&mut self) -> &mut SnapshotHistory;
+
+    /// Asks the builder to keep snapshots while it builds. Builders layered over
+    /// another one pass this on before building it.
+    fn record_snapshots(&mut self) {
+        self.history_mut().record();
+    }
+
+    fn get_snapshot_history(&self) -> Vec<MapSnapshot> {
+        self.history().snapshots().to_vec()
+    }
+
+    /// Records the map as it is now, if snapshots are being recorded.
+    fn take_snapshot(&mut self) {
+        if self.history().is_recording() {
+            let map = self.get_map();
+            self.history_mut().push(&map);
+        }
+    }
+
+    /// The rooms carved by builders that work in rooms, for others to build upon.
+    fn get_rooms(&self) -> Vec<Rect> {
+        Vec::new()
+    }
+}
+
+/// The names accepted by `builder_by_name`.
+pub const BUILDER_NAMES: &[&str] = &[
+    "simple",
+    "bsp",
+    "cellular",
+    "drunkard-open-area",
+    "drunkard-open-halls",
+    "drunkard-winding",
+    "dla-walk-inwards",
+    "dla-walk-outwards",
+    "dla-central-attractor",
+    "maze",
+    "prefab-fortress",
+    "prefab-vaults",
+    "prefab-orc-camp",
+    "wfc-caves",
+    "wfc-fortress",
+    "cellular-lakes",
+    "bsp-chasm",
+];
+
+/// The builder for the town on the surface, at depth 0.
+pub fn town_builder(width: i32, height: i32) -> Box<dyn MapBuilder> {
+    Box::new(TownBuilder::new(width, height))
+}
+
+/// Picks one of the available map builders at random, for a `width` x `height` map.
+pub fn random_builder(
+    rng: &mut RandomNumberGenerator,
+    width: i32,
+    height: i32,
+) -> Box<dyn MapBuilder> {
+    let roll = rng.roll_dice(1, BUILDER_NAMES.len() as i32) - 1;
+    builder_by_name(BUILDER_NAMES[roll as usize], width, height).unwrap()
+}
+
+/// Looks up a map builder by name, e.g. from a command-line option. Builders that
+/// load a whole level from a prefab use the prefab's size instead of `width` x `height`.
+pub fn builder_by_name(name: &str, width: i32, height: i32) -> Option<Box<dyn MapBuilder>> {
+    match name {
+        "simple" => Some(Box::new(SimpleMapBuilder::new(width, height))),
+        "bsp" => Some(Box::new(BspDungeonBuilder::new(width, height))),
+        "cellular" => Some(Box::new(CellularAutomataBuilder::new(width, height))),
+        "drunkard-open-area" => Some(Box::new(DrunkardsWalkBuilder::open_area(width, height))),
+        "drunkard-open-halls" => Some(Box::new(DrunkardsWalkBuilder::open_halls(width, height))),
+        "drunkard-winding" => Some(Box::new(DrunkardsWalkBuilder::winding_passages(
+            width, height,
+        ))),
+        "dla-walk-inwards" => Some(Box::new(DlaBuilder::walk_inwards(width, height))),
+        "dla-walk-outwards" => Some(Box::new(DlaBuilder::walk_outwards(width, height))),
+        "dla-central-attractor" => Some(Box::new(DlaBuilder::central_attractor(width, height))),
+        "maze" => Some(Box::new(MazeBuilder::new(width, height))),
+        "prefab-fortress" => Some(Box::new(PrefabBuilder::fortress())),
+        "prefab-vaults" => Some(Box::new(PrefabBuilder::room_vaults(Box::new(
+            SimpleMapBuilder::new(width, height),
+        )))),
+        "prefab-orc-camp" => Some(Box::new(PrefabBuilder::orc_camp(Box::new(
+            CellularAutomataBuilder::new(width, height),
+        )))),
+        "wfc-caves" => Some(Box::new(WaveformCollapseBuilder::new(
+            width,
+            height,
+            Box::new(CellularAutomataBuilder::new(width, height)),
+        ))),
+        "wfc-fortress" => Some(Box::new(WaveformCollapseBuilder::new(
+            width,
+            height,
+            Box::new(PrefabBuilder::fortress()),
+        ))),
+        "cellular-lakes" => Some(Box::new(TerrainBuilder::lakes(Box::new(
+            CellularAutomataBuilder::new(width, height),
+        )))),
+        "bsp-chasm" => Some(Box::new(TerrainBuilder::chasm(Box::new(
+            BspDungeonBuilder::new(width, height),
+        )))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_builder_name_is_known() {
+        for name in BUILDER_NAMES {
+            assert!(
+                builder_by_name(name, 80, 50).is_some(),
+                "{} is unknown",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_builder_name_is_rejected() {
+        assert!(builder_by_name("no-such-builder", 80, 50).is_none());
+    }
+
+    #[test]
+    fn snapshots_are_only_kept_when_recording() {
+        let mut builder = builder_by_name("simple", 80, 50).unwrap();
+        builder.build_map();
+        assert!(builder.get_snapshot_history().is_empty());
+
+        let mut builder = builder_by_name("simple", 80, 50).unwrap();
+        builder.record_snapshots();
+        builder.build_map();
+        let history = builder.get_snapshot_history();
+        assert!(!history.is_empty());
+        assert!(
+            history
+                .iter()
+                .all(|snapshot| snapshot.tiles.len() == 80 * 50)
+        );
+    }
+
+    #[test]
+    fn layered_builders_keep_the_snapshots_beneath_them() {
+        // The chasm itself takes at most two snapshots; the rest are the BSP rooms
+        let mut builder = builder_by_name("bsp-chasm", 80, 50).unwrap();
+        builder.record_snapshots();
+        builder.build_map();
+        assert!(builder.get_snapshot_history().len() > 2);
+    }
+}
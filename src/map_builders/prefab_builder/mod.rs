@@ -0,0 +1,338 @@
+use super::{
+    Map, MapBuilder, Position, Rect, SnapshotHistory, TileType, apply_horizontal_tunnel,
+    apply_vertical_tunnel, cull_unreachable_areas, spawner,
+};
+use rltk::rex::XpFile;
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+mod prefab_templates;
+use prefab_templates::*;
+
+/// How a prefab is applied to the map.
+pub enum PrefabMode {
+    /// The template is the whole level.
+    RexLevel { template: &'static str },
+    /// The template is stamped over one side of another builder's map.
+    Sectional { template: &'static str },
+    /// A few of another builder's rooms get a vault stamped into them.
+    RoomVaults,
+}
+
+/// Builds levels from REX Paint templates. Glyphs in the template map to tiles
+/// (`#` wall, `.` or space floor, `>` down stairs, `@` player start) or to entities
+/// spawned on a floor tile (`g` goblin, `o` orc, `!` potion, `^` trap, `+` door).
+pub struct PrefabBuilder {
+    map: Map,
+    starting_position: Position,
+    history: SnapshotHistory,
+    mode: PrefabMode,
+    previous_builder: Option<Box<dyn MapBuilder>>,
+    spawns: Vec<(usize, char)>,
+    stamped_areas: Vec<Rect>,
+}
+
+impl MapBuilder for PrefabBuilder {
+    fn build_map(&mut self) {
+        match self.mode {
+            PrefabMode::RexLevel { template } => self.load_rex_map(template),
+            PrefabMode::Sectional { template } => self.apply_sectional(template),
+            PrefabMode::RoomVaults => self.apply_room_vaults(),
+        }
+        self.take_snapshot();
+    }
+
+    fn spawn_entities(&mut self, ecs: &mut World) {
+        if let Some(previous_builder) = &mut self.previous_builder {
+            previous_builder.spawn_entities(ecs);
+            remove_entities_in_areas(ecs, &self.stamped_areas);
+        }
+
+        for (idx, glyph) in self.spawns.iter() {
+            let x = *idx as i32 % self.map.width;
+            let y = *idx as i32 / self.map.width;
+            match glyph {
+                'g' => spawner::goblin(ecs, x, y),
+                'o' => spawner::orc(ecs, x, y),
+                '!' => spawner::health_potion(ecs, x, y),
+                '^' => spawner::random_trap(ecs, x, y),
+                '+' => spawner::door(ecs, x, y),
+                _ => continue,
+            };
+        }
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn history(&self) -> &SnapshotHistory {
+        &self.history
+    }
+
+    fn history_mut(&mut self) -> &mut SnapshotHistory {
+        &mut self.history
+    }
+}
+
+impl PrefabBuilder {
+    pub fn new(mode: PrefabMode, previous_builder: Option<Box<dyn MapBuilder>>) -> PrefabBuilder {
+        PrefabBuilder {
+            map: Map::default(),
+            starting_position: Position { x: 0, y: 0 },
+            history: SnapshotHistory::default(),
+            mode,
+            previous_builder,
+            spawns: Vec::new(),
+            stamped_areas: Vec::new(),
+        }
+    }
+
+    pub fn fortress() -> PrefabBuilder {
+        PrefabBuilder::new(
+            PrefabMode::RexLevel {
+                template: FORTRESS_LEVEL,
+            },
+            None,
+        )
+    }
+
+    pub fn room_vaults(previous_builder: Box<dyn MapBuilder>) -> PrefabBuilder {
+        PrefabBuilder::new(PrefabMode::RoomVaults, Some(previous_builder))
+    }
+
+    pub fn orc_camp(previous_builder: Box<dyn MapBuilder>) -> PrefabBuilder {
+        PrefabBuilder::new(
+            PrefabMode::Sectional {
+                template: ORC_CAMP_SECTION,
+            },
+            Some(previous_builder),
+        )
+    }
+
+    /// Runs the builder this prefab is layered over and takes over its results.
+    fn build_previous(&mut self) {
+        let previous_builder = self
+            .previous_builder
+            .as_mut()
+            .expect("This prefab mode needs a map to build on");
+        if self.history.is_recording() {
+            previous_builder.record_snapshots();
+        }
+        previous_builder.build_map();
+        self.map = previous_builder.get_map();
+        self.starting_position = previous_builder.get_starting_position();
+        self.history = std::mem::take(previous_builder.history_mut());
+    }
+
+    fn char_to_map(&mut self, ch: char, idx: usize) {
+        match ch {
+            '#' => self.map.tiles[idx] = TileType::Wall,
+            '>' => self.map.tiles[idx] = TileType::DownStairs,
+            '@' => {
+                self.map.tiles[idx] = TileType::Floor;
+                self.starting_position = Position {
+                    x: idx as i32 % self.map.width,
+                    y: idx as i32 / self.map.width,
+                };
+            }
+            'g' | 'o' | '!' | '^' | '+' => {
+                self.map.tiles[idx] = TileType::Floor;
+                self.spawns.push((idx, ch));
+            }
+            ' ' | '.' => self.map.tiles[idx] = TileType::Floor,
+            _ => rltk::console::log(format!("Unknown glyph loading map: {}", ch)),
+        }
+    }
+
+    /// Copies a template onto the map with its top-left corner at (`x`, `y`).
+    fn stamp(&mut self, template: &XpFile, x: i32, y: i32) {
+        for layer in &template.layers {
+            for ty in 0..layer.height {
+                for tx in 0..layer.width {
+                    let map_x = x + tx as i32;
+                    let map_y = y + ty as i32;
+                    if map_x < 1
+                        || map_x > self.map.width - 2
+                        || map_y < 1
+                        || map_y > self.map.height - 2
+                    {
+                        continue;
+                    }
+                    if let Some(cell) = layer.get(tx, ty) {
+                        let idx = self.map.xy_idx(map_x, map_y);
+                        self.char_to_map(cell.ch as u8 as char, idx);
+                    }
+                }
+            }
+        }
+
+        let (width, height) = template_size(template);
+        self.stamped_areas
+            .push(Rect::new(x, y, width as i32 - 1, height as i32 - 1));
+    }
+
+    fn load_rex_map(&mut self, path: &str) {
+        let template = load_template(path);
+        let (width, height) = template_size(&template);
+        self.map = Map::new(width as i32, height as i32);
+        self.stamp(&template, 0, 0);
+    }
+
+    fn apply_sectional(&mut self, path: &str) {
+        self.build_previous();
+        let template = load_template(path);
+        let (width, height) = template_size(&template);
+
+        // Keep the section away from the player's starting point
+        let x = if self.starting_position.x < self.map.width / 2 {
+            self.map.width - width as i32 - 1
+        } else {
+            1
+        };
+        let y = (self.map.height - height as i32) / 2;
+        self.stamp(&template, x, y);
+        self.take_snapshot();
+
+        self.connect_stamped_areas();
+    }
+
+    fn apply_room_vaults(&mut self) {
+        const MAX_VAULTS: usize = 2;
+
+        self.build_previous();
+        let mut rng = RandomNumberGenerator::new();
+        let rooms = self
+            .previous_builder
+            .as_ref()
+            .map(|builder| builder.get_rooms())
+            .unwrap_or_default();
+
+        let mut vaults: Vec<&str> = ROOM_VAULTS.to_vec();
+        // The player starts in the first room and the stairs are in the last, so
+        // leave both alone
+        let last_room = rooms.last().copied();
+        for room in rooms.iter().skip(1) {
+            if vaults.is_empty() || self.stamped_areas.len() >= MAX_VAULTS {
+                break;
+            }
+            if Some(*room) == last_room {
+                continue;
+            }
+            if rng.roll_dice(1, 3) != 1 {
+                continue;
+            }
+
+            let vault_idx = (rng.roll_dice(1, vaults.len() as i32) - 1) as usize;
+            let template = load_template(vaults[vault_idx]);
+            let (width, height) = template_size(&template);
+            let floor_width = room.x2 - room.x1;
+            let floor_height = room.y2 - room.y1;
+            if width as i32 > floor_width || height as i32 > floor_height {
+                continue;
+            }
+
+            let x = room.x1 + rng.roll_dice(1, floor_width - width as i32 + 1);
+            let y = room.y1 + rng.roll_dice(1, floor_height - height as i32 + 1);
+            self.stamp(&template, x, y);
+            vaults.remove(vault_idx);
+            self.take_snapshot();
+        }
+
+        if !self.stamped_areas.is_empty() {
+            self.connect_stamped_areas();
+        }
+    }
+
+    /// A prefab can land anywhere, so tunnel from each stamped area to the closest
+    /// tile the player can reach, then tidy up anything that is still cut off along
+    /// with whatever was to be spawned there. If the prefab was stamped over the way
+    /// down, a new one goes on the furthest tile from the start.
+    fn connect_stamped_areas(&mut self) {
+        let start_idx = self
+            .map
+            .xy_idx(self.starting_position.x, self.starting_position.y);
+        self.map.populate_blocked();
+        let dijkstra_map = rltk::DijkstraMap::new(
+            self.map.width as usize,
+            self.map.height as usize,
+            &[start_idx],
+            &self.map,
+            f32::MAX,
+        );
+
+        let reachable: Vec<Point> = (0..self.map.tiles.len())
+            .filter(|idx| dijkstra_map.map[*idx] < f32::MAX)
+            .map(|idx| Point::new(idx as i32 % self.map.width, idx as i32 / self.map.width))
+            .collect();
+
+        for area in self.stamped_areas.clone() {
+            let mut closest: Option<(Point, Point, i32)> = None;
+            for y in area.y1..=area.y2 {
+                for x in area.x1..=area.x2 {
+                    if self.map.tiles[self.map.xy_idx(x, y)] == TileType::Wall {
+                        continue;
+                    }
+                    for target in reachable.iter() {
+                        let distance = (x - target.x).abs() + (y - target.y).abs();
+                        if closest.is_none_or(|(_, _, best)| distance < best) {
+                            closest = Some((Point::new(x, y), *target, distance));
+                        }
+                    }
+                }
+            }
+
+            if let Some((from, to, distance)) = closest
+                && distance > 0
+            {
+                apply_horizontal_tunnel(&mut self.map, from.x, to.x, from.y);
+                apply_vertical_tunnel(&mut self.map, from.y, to.y, to.x);
+            }
+        }
+
+        let exit_idx = cull_unreachable_areas(&mut self.map, start_idx);
+        let map = &self.map;
+        self.spawns
+            .retain(|(idx, _glyph)| map.tiles[*idx] == TileType::Floor);
+        if !self.map.tiles.contains(&TileType::DownStairs) {
+            self.map.tiles[exit_idx] = TileType::DownStairs;
+            self.take_snapshot();
+        }
+    }
+}
+
+fn template_size(template: &XpFile) -> (usize, usize) {
+    template
+        .layers
+        .first()
+        .map(|layer| (layer.width, layer.height))
+        .unwrap_or((0, 0))
+}
+
+/// Deletes whatever the previous builder spawned where a prefab has since been
+/// stamped, so the prefab's own contents aren't buried under them.
+fn remove_entities_in_areas(ecs: &mut World, areas: &[Rect]) {
+    let mut doomed: Vec<Entity> = Vec::new();
+    {
+        let player_entity = ecs.fetch::<Entity>();
+        let entities = ecs.entities();
+        let positions = ecs.read_storage::<Position>();
+        for (entity, pos) in (&entities, &positions).join() {
+            let inside = areas.iter().any(|area| {
+                pos.x >= area.x1 && pos.x <= area.x2 && pos.y >= area.y1 && pos.y <= area.y2
+            });
+            if inside && entity != *player_entity {
+                doomed.push(entity);
+            }
+        }
+    }
+
+    for entity in doomed {
+        ecs.delete_entity(entity)
+            .expect("Unable to delete entity under prefab");
+    }
+}
@@ -0,0 +1,43 @@
+use rltk::rex::XpFile;
+use std::sync::Once;
+
+rltk::embedded_resource!(FORTRESS, "../../../resources/fortress.xp");
+rltk::embedded_resource!(VAULT_GOBLIN_DEN, "../../../resources/vault_goblin_den.xp");
+rltk::embedded_resource!(
+    VAULT_TRAPPED_CACHE,
+    "../../../resources/vault_trapped_cache.xp"
+);
+rltk::embedded_resource!(
+    VAULT_PILLARED_HALL,
+    "../../../resources/vault_pillared_hall.xp"
+);
+rltk::embedded_resource!(SECTION_ORC_CAMP, "../../../resources/section_orc_camp.xp");
+
+/// A complete, hand-drawn level. The map takes the template's size rather than
+/// the size the other builders are asked for.
+pub const FORTRESS_LEVEL: &str = "resources/fortress.xp";
+
+/// Small set pieces that can be dropped into the floor of a room.
+pub const ROOM_VAULTS: &[&str] = &[
+    "resources/vault_goblin_den.xp",
+    "resources/vault_trapped_cache.xp",
+    "resources/vault_pillared_hall.xp",
+];
+
+/// A walled orc camp, stamped over one side of another builder's map.
+pub const ORC_CAMP_SECTION: &str = "resources/section_orc_camp.xp";
+
+static LINK_RESOURCES: Once = Once::new();
+
+/// Loads a REX Paint template that was embedded into the binary.
+pub fn load_template(path: &str) -> XpFile {
+    LINK_RESOURCES.call_once(|| {
+        rltk::link_resource!(FORTRESS, "resources/fortress.xp");
+        rltk::link_resource!(VAULT_GOBLIN_DEN, "resources/vault_goblin_den.xp");
+        rltk::link_resource!(VAULT_TRAPPED_CACHE, "resources/vault_trapped_cache.xp");
+        rltk::link_resource!(VAULT_PILLARED_HALL, "resources/vault_pillared_hall.xp");
+        rltk::link_resource!(SECTION_ORC_CAMP, "resources/section_orc_camp.xp");
+    });
+
+    XpFile::from_resource(path).expect("Unable to load prefab template")
+}
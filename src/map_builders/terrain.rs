@@ -0,0 +1,246 @@
+use super::{Map, MapBuilder, Position, SnapshotHistory, TileType};
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+/// Which features `TerrainBuilder` adds to the map it is layered over.
+pub enum TerrainFeature {
+    /// Pools of water, deep in the middle, and a few smaller pools of lava.
+    Lakes,
+    /// A chasm cutting across the level, crossed by bridges.
+    Chasm,
+}
+
+/// Floods part of another builder's map with water, lava or a chasm. Features that
+/// would leave part of the level out of reach, or only reachable through lava, are
+/// taken back out again.
+pub struct TerrainBuilder {
+    map: Map,
+    starting_position: Position,
+    history: SnapshotHistory,
+    feature: TerrainFeature,
+    previous_builder: Box<dyn MapBuilder>,
+}
+
+impl MapBuilder for TerrainBuilder {
+    fn build_map(&mut self) {
+        let mut rng = RandomNumberGenerator::new();
+
+        if self.history.is_recording() {
+            self.previous_builder.record_snapshots();
+        }
+        self.previous_builder.build_map();
+        self.map = self.previous_builder.get_map();
+        self.starting_position = self.previous_builder.get_starting_position();
+        self.history = std::mem::take(self.previous_builder.history_mut());
+
+        match self.feature {
+            TerrainFeature::Lakes => self.add_lakes(&mut rng),
+            TerrainFeature::Chasm => self.add_chasm(&mut rng),
+        }
+        self.take_snapshot();
+    }
+
+    fn spawn_entities(&mut self, ecs: &mut World) {
+        self.previous_builder.spawn_entities(ecs);
+        remove_entities_off_floor(ecs, &self.map);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn history(&self) -> &SnapshotHistory {
+        &self.history
+    }
+
+    fn history_mut(&mut self) -> &mut SnapshotHistory {
+        &mut self.history
+    }
+}
+
+impl TerrainBuilder {
+    pub fn new(feature: TerrainFeature, previous_builder: Box<dyn MapBuilder>) -> TerrainBuilder {
+        TerrainBuilder {
+            map: Map::default(),
+            starting_position: Position { x: 0, y: 0 },
+            history: SnapshotHistory::default(),
+            feature,
+            previous_builder,
+        }
+    }
+
+    pub fn lakes(previous_builder: Box<dyn MapBuilder>) -> TerrainBuilder {
+        TerrainBuilder::new(TerrainFeature::Lakes, previous_builder)
+    }
+
+    pub fn chasm(previous_builder: Box<dyn MapBuilder>) -> TerrainBuilder {
+        TerrainBuilder::new(TerrainFeature::Chasm, previous_builder)
+    }
+
+    fn add_lakes(&mut self, rng: &mut RandomNumberGenerator) {
+        const TILES_PER_LAKE: usize = 1500;
+
+        let lake_count = (self.map.tiles.len() / TILES_PER_LAKE).max(1);
+        for _i in 0..lake_count {
+            let radius = rng.roll_dice(1, 3) + 2;
+            self.add_pool(rng, TileType::DeepWater, radius);
+        }
+        for _i in 0..lake_count.div_ceil(2) {
+            let radius = rng.roll_dice(1, 2);
+            self.add_pool(rng, TileType::Lava, radius);
+        }
+    }
+
+    /// Floods the floor within `radius` of a random floor tile. Deep water gets a
+    /// shallow rim that can be waded through.
+    fn add_pool(&mut self, rng: &mut RandomNumberGenerator, liquid: TileType, radius: i32) {
+        const MAX_TRIES: i32 = 20;
+
+        let start = Point::new(self.starting_position.x, self.starting_position.y);
+        let center = (0..MAX_TRIES)
+            .map(|_try| {
+                Point::new(
+                    rng.roll_dice(1, self.map.width - 2),
+                    rng.roll_dice(1, self.map.height - 2),
+                )
+            })
+            .find(|point| {
+                self.map.tiles[self.map.xy_idx(point.x, point.y)] == TileType::Floor
+                    && rltk::DistanceAlg::Pythagoras.distance2d(*point, start) > (radius + 3) as f32
+            });
+        let Some(center) = center else {
+            return;
+        };
+
+        let before = self.map.clone();
+        for y in (center.y - radius).max(1)..=(center.y + radius).min(self.map.height - 2) {
+            for x in (center.x - radius).max(1)..=(center.x + radius).min(self.map.width - 2) {
+                let idx = self.map.xy_idx(x, y);
+                let distance = rltk::DistanceAlg::Pythagoras.distance2d(Point::new(x, y), center);
+                if self.map.tiles[idx] != TileType::Floor || distance > radius as f32 {
+                    continue;
+                }
+                self.map.tiles[idx] =
+                    if liquid == TileType::DeepWater && distance > radius as f32 - 1.5 {
+                        TileType::ShallowWater
+                    } else {
+                        liquid
+                    };
+            }
+        }
+
+        if self.all_safe_tiles_reachable() {
+            self.take_snapshot();
+        } else {
+            self.map = before;
+        }
+    }
+
+    /// Cuts a chasm from top to bottom somewhere near the middle of the map, with a
+    /// bridge across wherever there is floor on both sides of it.
+    fn add_chasm(&mut self, rng: &mut RandomNumberGenerator) {
+        const CHASM_WIDTH: i32 = 3;
+        const MAX_TRIES: i32 = 10;
+
+        for _try in 0..MAX_TRIES {
+            let before = self.map.clone();
+            let x1 = self.map.width / 3 + rng.roll_dice(1, self.map.width / 3);
+            let x2 = x1 + CHASM_WIDTH - 1;
+
+            for y in 1..self.map.height - 1 {
+                for x in x1..=x2 {
+                    let idx = self.map.xy_idx(x, y);
+                    if self.map.tiles[idx] == TileType::Floor {
+                        self.map.tiles[idx] = TileType::Chasm;
+                    }
+                }
+            }
+
+            // Put a bridge in the middle of each stretch of rows that can be crossed
+            let crossable: Vec<bool> = (0..self.map.height)
+                .map(|y| {
+                    self.map.tiles[self.map.xy_idx(x1 - 1, y)].is_walkable()
+                        && self.map.tiles[self.map.xy_idx(x2 + 1, y)].is_walkable()
+                        && (x1..=x2)
+                            .all(|x| self.map.tiles[self.map.xy_idx(x, y)] == TileType::Chasm)
+                })
+                .collect();
+            let mut stretch_start: Option<i32> = None;
+            for y in 0..self.map.height {
+                if crossable[y as usize] {
+                    stretch_start.get_or_insert(y);
+                } else if let Some(first_y) = stretch_start.take() {
+                    let bridge_y = (first_y + y - 1) / 2;
+                    for x in x1..=x2 {
+                        let idx = self.map.xy_idx(x, bridge_y);
+                        self.map.tiles[idx] = TileType::Bridge;
+                    }
+                }
+            }
+
+            if self.all_safe_tiles_reachable() {
+                self.take_snapshot();
+                return;
+            }
+            self.map = before;
+        }
+    }
+
+    /// Whether the player can still get to every tile that is safe to stand on
+    /// without crossing anything that would hurt them.
+    fn all_safe_tiles_reachable(&self) -> bool {
+        let map = &self.map;
+        let is_safe =
+            |idx: usize| map.tiles[idx].is_walkable() && map.tiles[idx].damage_per_turn() == 0;
+
+        let start_idx = map.xy_idx(self.starting_position.x, self.starting_position.y);
+        if !is_safe(start_idx) {
+            return false;
+        }
+
+        let mut reached = vec![false; map.tiles.len()];
+        let mut open_list = vec![start_idx];
+        reached[start_idx] = true;
+        while let Some(idx) = open_list.pop() {
+            let x = idx as i32 % map.width;
+            let y = idx as i32 / map.width;
+            for neighbor_y in (y - 1).max(0)..=(y + 1).min(map.height - 1) {
+                for neighbor_x in (x - 1).max(0)..=(x + 1).min(map.width - 1) {
+                    let neighbor_idx = map.xy_idx(neighbor_x, neighbor_y);
+                    if !reached[neighbor_idx] && is_safe(neighbor_idx) {
+                        reached[neighbor_idx] = true;
+                        open_list.push(neighbor_idx);
+                    }
+                }
+            }
+        }
+
+        (0..map.tiles.len()).all(|idx| reached[idx] || !is_safe(idx))
+    }
+}
+
+/// Deletes whatever the previous builder spawned on tiles that have since been
+/// flooded or dropped into a chasm.
+fn remove_entities_off_floor(ecs: &mut World, map: &Map) {
+    let mut doomed: Vec<Entity> = Vec::new();
+    {
+        let player_entity = ecs.fetch::<Entity>();
+        let entities = ecs.entities();
+        let positions = ecs.read_storage::<Position>();
+        for (entity, pos) in (&entities, &positions).join() {
+            let tile = map.tiles[map.xy_idx(pos.x, pos.y)];
+            if tile != TileType::Floor && tile != TileType::DownStairs && entity != *player_entity {
+                doomed.push(entity);
+            }
+        }
+    }
+
+    for entity in doomed {
+        ecs.delete_entity(entity)
+            .expect("Unable to delete entity off the floor");
+    }
+}
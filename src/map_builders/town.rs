@@ -0,0 +1,176 @@
+use super::{Map, MapBuilder, Position, Rect, SnapshotHistory, TileType, spawner};
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+/// Makes the town on the surface: a road running from the west edge to the way
+/// down in the east, with buildings along both sides of it. The building closest to
+/// where the player arrives is a shop.
+pub struct TownBuilder {
+    map: Map,
+    starting_position: Position,
+    history: SnapshotHistory,
+    buildings: Vec<Rect>,
+    doors: Vec<usize>,
+}
+
+impl MapBuilder for TownBuilder {
+    fn build_map(&mut self) {
+        let mut rng = RandomNumberGenerator::new();
+
+        let road_y = self.map.height / 2;
+        self.lay_grass_and_road(road_y);
+        self.take_snapshot();
+
+        self.place_buildings(road_y, &mut rng);
+
+        self.starting_position = Position { x: 2, y: road_y };
+        let stairs_idx = self.map.xy_idx(self.map.width - 3, road_y);
+        self.map.tiles[stairs_idx] = TileType::DownStairs;
+        self.take_snapshot();
+    }
+
+    fn spawn_entities(&mut self, ecs: &mut World) {
+        const SHOP_STOCK: i32 = 4;
+
+        for idx in self.doors.iter() {
+            let x = *idx as i32 % self.map.width;
+            let y = *idx as i32 / self.map.width;
+            spawner::door(ecs, x, y);
+        }
+
+        let start = Point::new(self.starting_position.x, self.starting_position.y);
+        let shop = self.buildings.iter().min_by(|a, b| {
+            let (ax, ay) = a.center();
+            let (bx, by) = b.center();
+            let distance_a = rltk::DistanceAlg::Pythagoras.distance2d(Point::new(ax, ay), start);
+            let distance_b = rltk::DistanceAlg::Pythagoras.distance2d(Point::new(bx, by), start);
+            distance_a.total_cmp(&distance_b)
+        });
+        if let Some(shop) = shop {
+            let (x, y) = shop.center();
+            let alchemist = spawner::vendor(ecs, x, y, "Alchemist");
+            for _i in 0..SHOP_STOCK {
+                let potion = spawner::health_potion(ecs, x, y);
+                spawner::give_item(ecs, alchemist, potion);
+            }
+        }
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn history(&self) -> &SnapshotHistory {
+        &self.history
+    }
+
+    fn history_mut(&mut self) -> &mut SnapshotHistory {
+        &mut self.history
+    }
+}
+
+impl TownBuilder {
+    pub fn new(width: i32, height: i32) -> TownBuilder {
+        TownBuilder {
+            map: Map::new(width, height),
+            starting_position: Position { x: 0, y: 0 },
+            history: SnapshotHistory::default(),
+            buildings: Vec::new(),
+            doors: Vec::new(),
+        }
+    }
+
+    /// Covers everything inside the town wall with grass, then runs a three tile wide
+    /// road across the middle.
+    fn lay_grass_and_road(&mut self, road_y: i32) {
+        for y in 1..self.map.height - 1 {
+            for x in 1..self.map.width - 1 {
+                let idx = self.map.xy_idx(x, y);
+                self.map.tiles[idx] = if (y - road_y).abs() <= 1 {
+                    TileType::Floor
+                } else {
+                    TileType::Grass
+                };
+            }
+        }
+    }
+
+    /// Puts up as many buildings as will fit on either side of the road. Each has a
+    /// door facing the road and a path leading to it, and nothing else may be built
+    /// between a building and the road.
+    fn place_buildings(&mut self, road_y: i32, rng: &mut RandomNumberGenerator) {
+        const ATTEMPTS: i32 = 200;
+        const MIN_WIDTH: i32 = 6;
+        const MAX_WIDTH: i32 = 12;
+        const MIN_HEIGHT: i32 = 4;
+        const MAX_HEIGHT: i32 = 7;
+        const GAP_TO_ROAD: i32 = 3;
+
+        let mut claimed: Vec<Rect> = Vec::new();
+        for _i in 0..ATTEMPTS {
+            let w = rng.range(MIN_WIDTH, MAX_WIDTH + 1);
+            let h = rng.range(MIN_HEIGHT, MAX_HEIGHT + 1);
+            let x = rng.range(3, self.map.width - w - 3);
+            let north = rng.range(0, 2) == 0;
+            let (lowest_y, highest_y) = if north {
+                (2, road_y - 1 - GAP_TO_ROAD - h)
+            } else {
+                (road_y + 1 + GAP_TO_ROAD, self.map.height - 3 - h)
+            };
+            if highest_y <= lowest_y {
+                continue;
+            }
+            let building = Rect::new(x, rng.range(lowest_y, highest_y), w, h);
+
+            // The building's plot runs all the way to the road, leaving room for its path
+            let plot = if north {
+                Rect::new(x - 1, building.y1 - 1, w + 2, road_y - building.y1)
+            } else {
+                Rect::new(x - 1, road_y, w + 2, building.y2 + 1 - road_y)
+            };
+            if claimed.iter().any(|other| plot.intersect(other)) {
+                continue;
+            }
+            claimed.push(plot);
+
+            self.build(&building, north, road_y);
+            self.buildings.push(building);
+            self.take_snapshot();
+        }
+    }
+
+    /// Walls in a building with a floor inside, then adds a door on the side facing
+    /// the road and a path from there to the road.
+    fn build(&mut self, building: &Rect, north: bool, road_y: i32) {
+        for y in building.y1..=building.y2 {
+            for x in building.x1..=building.x2 {
+                let idx = self.map.xy_idx(x, y);
+                let on_wall =
+                    x == building.x1 || x == building.x2 || y == building.y1 || y == building.y2;
+                self.map.tiles[idx] = if on_wall {
+                    TileType::Wall
+                } else {
+                    TileType::Floor
+                };
+            }
+        }
+
+        let (door_x, _) = building.center();
+        let (door_y, path) = if north {
+            (building.y2, building.y2 + 1..road_y - 1)
+        } else {
+            (building.y1, road_y + 2..building.y1)
+        };
+        let door_idx = self.map.xy_idx(door_x, door_y);
+        self.map.tiles[door_idx] = TileType::Floor;
+        self.doors.push(door_idx);
+        for y in path {
+            let idx = self.map.xy_idx(door_x, y);
+            self.map.tiles[idx] = TileType::Floor;
+        }
+    }
+}
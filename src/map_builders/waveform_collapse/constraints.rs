@@ -0,0 +1,128 @@
+use super::{Map, TileType};
+use std::collections::HashSet;
+
+/// Edges of a chunk, used to index `MapChunk::exits` and `MapChunk::compatible_with`.
+pub const NORTH: usize = 0;
+pub const SOUTH: usize = 1;
+pub const WEST: usize = 2;
+pub const EAST: usize = 3;
+
+pub fn opposite(direction: usize) -> usize {
+    match direction {
+        NORTH => SOUTH,
+        SOUTH => NORTH,
+        WEST => EAST,
+        _ => WEST,
+    }
+}
+
+/// A square pattern of tiles learned from a source map, along with the indices of
+/// the chunks that may sit next to it on each side.
+#[derive(Clone)]
+pub struct MapChunk {
+    pub pattern: Vec<TileType>,
+    pub exits: [Vec<bool>; 4],
+    pub compatible_with: [HashSet<usize>; 4],
+}
+
+/// Cuts the source map into `chunk_size` squares, adding flipped copies of each
+/// for variety, and returns the distinct patterns found.
+pub fn build_patterns(map: &Map, chunk_size: i32) -> Vec<Vec<TileType>> {
+    let chunks_x = map.width / chunk_size;
+    let chunks_y = map.height / chunk_size;
+    let mut patterns: HashSet<Vec<TileType>> = HashSet::new();
+
+    for cy in 0..chunks_y {
+        for cx in 0..chunks_x {
+            let start_x = cx * chunk_size;
+            let start_y = cy * chunk_size;
+
+            for (flip_x, flip_y) in [(false, false), (true, false), (false, true), (true, true)] {
+                let mut pattern: Vec<TileType> = Vec::new();
+                for y in 0..chunk_size {
+                    for x in 0..chunk_size {
+                        let source_x = if flip_x { chunk_size - 1 - x } else { x };
+                        let source_y = if flip_y { chunk_size - 1 - y } else { y };
+                        let idx = map.xy_idx(start_x + source_x, start_y + source_y);
+                        // Only the shape matters, so anything walkable counts as floor
+                        pattern.push(match map.tiles[idx] {
+                            TileType::Wall => TileType::Wall,
+                            _ => TileType::Floor,
+                        });
+                    }
+                }
+                patterns.insert(pattern);
+            }
+        }
+    }
+
+    patterns.into_iter().collect()
+}
+
+/// Works out which patterns can be placed next to each other. A side with openings
+/// accepts neighbors whose facing side is open in at least one of the same places;
+/// a solid side doesn't care what it faces.
+pub fn patterns_to_constraints(patterns: Vec<Vec<TileType>>, chunk_size: i32) -> Vec<MapChunk> {
+    let size = chunk_size as usize;
+    let is_floor =
+        |pattern: &[TileType], x: usize, y: usize| pattern[y * size + x] == TileType::Floor;
+
+    let mut constraints: Vec<MapChunk> = patterns
+        .into_iter()
+        .map(|pattern| {
+            let exits = [
+                (0..size).map(|x| is_floor(&pattern, x, 0)).collect(),
+                (0..size).map(|x| is_floor(&pattern, x, size - 1)).collect(),
+                (0..size).map(|y| is_floor(&pattern, 0, y)).collect(),
+                (0..size).map(|y| is_floor(&pattern, size - 1, y)).collect(),
+            ];
+            MapChunk {
+                pattern,
+                exits,
+                compatible_with: Default::default(),
+            }
+        })
+        .collect();
+
+    for chunk_idx in 0..constraints.len() {
+        for direction in [NORTH, SOUTH, WEST, EAST] {
+            let exits = &constraints[chunk_idx].exits[direction];
+            let compatible: HashSet<usize> = if exits.iter().any(|exit| *exit) {
+                constraints
+                    .iter()
+                    .enumerate()
+                    .filter(|(_idx, other)| {
+                        exits
+                            .iter()
+                            .zip(other.exits[opposite(direction)].iter())
+                            .any(|(ours, theirs)| *ours && *theirs)
+                    })
+                    .map(|(idx, _other)| idx)
+                    .collect()
+            } else {
+                (0..constraints.len()).collect()
+            };
+            constraints[chunk_idx].compatible_with[direction] = compatible;
+        }
+    }
+
+    constraints
+}
+
+/// Draws a chunk onto the map with its top-left corner at (`start_x`, `start_y`).
+pub fn render_pattern_to_map(
+    map: &mut Map,
+    chunk: &MapChunk,
+    chunk_size: i32,
+    start_x: i32,
+    start_y: i32,
+) {
+    let mut i = 0;
+    for y in 0..chunk_size {
+        for x in 0..chunk_size {
+            let idx = map.xy_idx(start_x + x, start_y + y);
+            map.tiles[idx] = chunk.pattern[i];
+            i += 1;
+        }
+    }
+}
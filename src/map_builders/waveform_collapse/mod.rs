@@ -0,0 +1,153 @@
+use super::{
+    Map, MapBuilder, Position, SnapshotHistory, TileType, cull_unreachable_areas,
+    spawn_voronoi_regions,
+};
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+mod constraints;
+use constraints::{build_patterns, patterns_to_constraints};
+
+mod solver;
+use solver::{Solver, SolverStep};
+
+/// Makes a new map with wave function collapse: another builder's map is cut into
+/// chunks, the solver learns which chunks can sit next to each other, and then it
+/// stitches together a brand new map that obeys the same rules.
+pub struct WaveformCollapseBuilder {
+    map: Map,
+    starting_position: Position,
+    history: SnapshotHistory,
+    source_builder: Box<dyn MapBuilder>,
+}
+
+impl MapBuilder for WaveformCollapseBuilder {
+    fn build_map(&mut self) {
+        const CHUNK_SIZE: i32 = 8;
+        const MAX_ATTEMPTS: i32 = 20;
+        const MIN_FLOOR_PERCENT: f32 = 0.2;
+
+        let mut rng = RandomNumberGenerator::new();
+
+        let (width, height) = (self.map.width, self.map.height);
+        self.source_builder.build_map();
+        let source_map = self.source_builder.get_map();
+        self.map = source_map.clone();
+        self.take_snapshot();
+
+        let patterns = build_patterns(&source_map, CHUNK_SIZE);
+        let constraints = patterns_to_constraints(patterns, CHUNK_SIZE);
+
+        for _attempt in 0..MAX_ATTEMPTS {
+            self.map = Map::new(width, height);
+            let mut solver = Solver::new(constraints.clone(), CHUNK_SIZE, &self.map);
+            let solved = loop {
+                match solver.iteration(&mut self.map, &mut rng) {
+                    SolverStep::Placed => self.take_snapshot(),
+                    SolverStep::Finished => break true,
+                    SolverStep::Contradiction => break false,
+                }
+            };
+
+            if solved && self.finalize() >= MIN_FLOOR_PERCENT {
+                self.take_snapshot();
+                return;
+            }
+        }
+
+        // The patterns just won't tile; fall back to the map we learned them from
+        rltk::console::log("Wave function collapse gave up, using its source map");
+        self.map = source_map;
+        self.finalize();
+        self.take_snapshot();
+    }
+
+    fn spawn_entities(&mut self, ecs: &mut World) {
+        spawn_voronoi_regions(ecs, &self.map, &self.starting_position);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn history(&self) -> &SnapshotHistory {
+        &self.history
+    }
+
+    fn history_mut(&mut self) -> &mut SnapshotHistory {
+        &mut self.history
+    }
+}
+
+impl WaveformCollapseBuilder {
+    pub fn new(
+        width: i32,
+        height: i32,
+        source_builder: Box<dyn MapBuilder>,
+    ) -> WaveformCollapseBuilder {
+        WaveformCollapseBuilder {
+            map: Map::new(width, height),
+            starting_position: Position { x: 0, y: 0 },
+            history: SnapshotHistory::default(),
+            source_builder,
+        }
+    }
+
+    /// Seals the map's edge, picks a start near the middle, walls off whatever can't
+    /// be reached and places the exit. Returns the fraction of the map left open.
+    fn finalize(&mut self) -> f32 {
+        for x in 0..self.map.width {
+            let top = self.map.xy_idx(x, 0);
+            let bottom = self.map.xy_idx(x, self.map.height - 1);
+            self.map.tiles[top] = TileType::Wall;
+            self.map.tiles[bottom] = TileType::Wall;
+        }
+        for y in 0..self.map.height {
+            let left = self.map.xy_idx(0, y);
+            let right = self.map.xy_idx(self.map.width - 1, y);
+            self.map.tiles[left] = TileType::Wall;
+            self.map.tiles[right] = TileType::Wall;
+        }
+        for tile in self.map.tiles.iter_mut() {
+            if *tile == TileType::DownStairs {
+                *tile = TileType::Floor;
+            }
+        }
+
+        let center = Point::new(self.map.width / 2, self.map.height / 2);
+        let Some(start_idx) = self
+            .map
+            .tiles
+            .iter()
+            .enumerate()
+            .filter(|(_idx, tile)| **tile == TileType::Floor)
+            .map(|(idx, _tile)| idx)
+            .min_by_key(|idx| {
+                let x = *idx as i32 % self.map.width;
+                let y = *idx as i32 / self.map.width;
+                (x - center.x).pow(2) + (y - center.y).pow(2)
+            })
+        else {
+            return 0.0;
+        };
+        self.starting_position = Position {
+            x: start_idx as i32 % self.map.width,
+            y: start_idx as i32 / self.map.width,
+        };
+
+        let exit_idx = cull_unreachable_areas(&mut self.map, start_idx);
+        self.map.tiles[exit_idx] = TileType::DownStairs;
+
+        let open_tiles = self
+            .map
+            .tiles
+            .iter()
+            .filter(|tile| **tile != TileType::Wall)
+            .count();
+        open_tiles as f32 / self.map.tiles.len() as f32
+    }
+}
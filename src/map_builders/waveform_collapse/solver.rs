@@ -0,0 +1,111 @@
+use super::Map;
+use super::constraints::{EAST, MapChunk, NORTH, SOUTH, WEST, opposite, render_pattern_to_map};
+use rltk::RandomNumberGenerator;
+
+pub enum SolverStep {
+    /// A chunk was placed; call `iteration` again.
+    Placed,
+    /// Every chunk has been placed.
+    Finished,
+    /// Some chunk has no pattern that fits its neighbors, so the attempt is dead.
+    Contradiction,
+}
+
+/// Fills the map one chunk at a time, always choosing the open slot with the fewest
+/// patterns that still fit, so that contradictions surface as early as possible.
+pub struct Solver {
+    constraints: Vec<MapChunk>,
+    chunk_size: i32,
+    chunks: Vec<Option<usize>>,
+    chunks_x: i32,
+    chunks_y: i32,
+}
+
+impl Solver {
+    pub fn new(constraints: Vec<MapChunk>, chunk_size: i32, map: &Map) -> Solver {
+        let chunks_x = map.width / chunk_size;
+        let chunks_y = map.height / chunk_size;
+        Solver {
+            constraints,
+            chunk_size,
+            chunks: vec![None; (chunks_x * chunks_y) as usize],
+            chunks_x,
+            chunks_y,
+        }
+    }
+
+    fn chunk_idx(&self, x: i32, y: i32) -> usize {
+        (y * self.chunks_x + x) as usize
+    }
+
+    /// The patterns that agree with every neighbor already placed around (`x`, `y`).
+    fn options(&self, x: i32, y: i32) -> Vec<usize> {
+        let neighbors = [
+            (NORTH, x, y - 1),
+            (SOUTH, x, y + 1),
+            (WEST, x - 1, y),
+            (EAST, x + 1, y),
+        ];
+
+        (0..self.constraints.len())
+            .filter(|candidate| {
+                neighbors.iter().all(|(direction, nx, ny)| {
+                    if *nx < 0 || *nx >= self.chunks_x || *ny < 0 || *ny >= self.chunks_y {
+                        return true;
+                    }
+                    match self.chunks[self.chunk_idx(*nx, *ny)] {
+                        None => true,
+                        Some(neighbor) => {
+                            self.constraints[*candidate].compatible_with[*direction]
+                                .contains(&neighbor)
+                                && self.constraints[neighbor].compatible_with[opposite(*direction)]
+                                    .contains(candidate)
+                        }
+                    }
+                })
+            })
+            .collect()
+    }
+
+    pub fn iteration(&mut self, map: &mut Map, rng: &mut RandomNumberGenerator) -> SolverStep {
+        // Find the empty slot that is most constrained by its neighbors
+        let mut best: Option<(i32, i32, Vec<usize>)> = None;
+        for y in 0..self.chunks_y {
+            for x in 0..self.chunks_x {
+                if self.chunks[self.chunk_idx(x, y)].is_some() {
+                    continue;
+                }
+                let options = self.options(x, y);
+                if options.is_empty() {
+                    return SolverStep::Contradiction;
+                }
+                let fewer_options = match &best {
+                    None => true,
+                    Some((_, _, best_options)) => {
+                        options.len() < best_options.len()
+                            || (options.len() == best_options.len() && rng.roll_dice(1, 4) == 1)
+                    }
+                };
+                if fewer_options {
+                    best = Some((x, y, options));
+                }
+            }
+        }
+
+        let Some((x, y, options)) = best else {
+            return SolverStep::Finished;
+        };
+
+        let chosen = options[(rng.roll_dice(1, options.len() as i32) - 1) as usize];
+        let idx = self.chunk_idx(x, y);
+        self.chunks[idx] = Some(chosen);
+        render_pattern_to_map(
+            map,
+            &self.constraints[chosen],
+            self.chunk_size,
+            x * self.chunk_size,
+            y * self.chunk_size,
+        );
+        SolverStep::Placed
+    }
+}
@@ -1,8 +1,27 @@
-use super::{CombatStats, Name, SufferDamage, WantsToMelee, gamelog::GameLog};
+use super::{
+    CombatStats, Name, SufferDamage, WantsToMelee,
+    gamelog::{GameLog, LogFragment},
+};
+use rltk::RGB;
 use specs::prelude::*;
 
 pub struct MeleeCombatSystem {}
 
+/// How hard an entity hits in melee.
+pub fn attack_power(stats: &CombatStats) -> i32 {
+    stats.power
+}
+
+/// How much of each melee hit an entity shrugs off.
+pub fn defense(stats: &CombatStats) -> i32 {
+    stats.defense
+}
+
+/// The damage one melee hit from `attacker` does to `target`.
+pub fn melee_damage(attacker: &CombatStats, target: &CombatStats) -> i32 {
+    i32::max(0, attack_power(attacker) - defense(target))
+}
+
 impl<'a> System<'a> for MeleeCombatSystem {
     type SystemData = (
         Entities<'a>,
@@ -24,18 +43,22 @@ impl<'a> System<'a> for MeleeCombatSystem {
                 if target_stats.hp > 0 {
                     let target_name = names.get(wants_melee.target).unwrap();
 
-                    let damage = i32::max(0, stats.power - target_stats.defense);
+                    let damage = melee_damage(stats, target_stats);
 
                     if damage == 0 {
-                        log.entries.push(format!(
+                        log.add(format!(
                             "{} is unable to hurt {}!",
                             &name.name, &target_name.name
                         ));
                     } else {
-                        log.entries.push(format!(
-                            "{} hits {} for {} hp.",
-                            &name.name, &target_name.name, damage
-                        ));
+                        log.add_fragments(vec![
+                            LogFragment::plain(format!(
+                                "{} hits {} for ",
+                                &name.name, &target_name.name
+                            )),
+                            LogFragment::new(format!("{} hp", damage), RGB::named(rltk::RED)),
+                            LogFragment::plain("."),
+                        ]);
                         SufferDamage::new_damage(&mut inflict_damage, wants_melee.target, damage);
                     }
                 }
@@ -1,6 +1,9 @@
 use crate::RunState;
 
-use super::{Map, Monster, Position, Viewshed, WantsToMelee};
+use super::{
+    BlocksTile, BlocksVisibility, Door, EntityMoved, Map, Monster, Position, Renderable, Viewshed,
+    WantsToMelee,
+};
 use rltk::Point;
 use specs::prelude::*;
 
@@ -18,6 +21,11 @@ impl<'a> System<'a> for MonsterAI {
         ReadStorage<'a, Monster>,
         WriteStorage<'a, Position>,
         WriteStorage<'a, WantsToMelee>,
+        WriteStorage<'a, Door>,
+        WriteStorage<'a, BlocksVisibility>,
+        WriteStorage<'a, BlocksTile>,
+        WriteStorage<'a, Renderable>,
+        WriteStorage<'a, EntityMoved>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -31,12 +39,30 @@ impl<'a> System<'a> for MonsterAI {
             monster,
             mut position,
             mut wants_to_melee,
+            mut doors,
+            mut blocks_visibility,
+            mut blocks_movement,
+            mut renderables,
+            mut entity_moved,
         ) = data;
 
         if *runstate != RunState::MonsterTurn {
             return;
         }
 
+        // Monsters know how to open doors, so closed doors shouldn't stop them pathing
+        let mut closed_doors: Vec<(Entity, usize)> = Vec::new();
+        for (door_entity, door) in (&entities, &doors).join() {
+            if !door.open
+                && let Some(door_pos) = position.get(door_entity)
+            {
+                let idx = map.xy_idx(door_pos.x, door_pos.y);
+                map.blocked[idx] = false;
+                closed_doors.push((door_entity, idx));
+            }
+        }
+
+        let mut opened_doors: Vec<Point> = Vec::new();
         for (entity, viewshed, _monster, pos) in
             (&entities, &mut viewshed, &monster, &mut position).join()
         {
@@ -56,9 +82,27 @@ impl<'a> System<'a> for MonsterAI {
                 let path = rltk::a_star_search(
                     map.xy_idx(pos.x, pos.y),
                     map.xy_idx(player_pos.x, player_pos.y),
-                    &mut *map,
+                    &*map,
                 );
-                if path.success && path.steps.len() > 1 {
+                let door_in_the_way = closed_doors.iter().find(|(_door, idx)| {
+                    path.success && path.steps.len() > 1 && *idx == path.steps[1]
+                });
+
+                if let Some((door_entity, _idx)) = door_in_the_way {
+                    // Spend the turn opening the door
+                    if let Some(door) = doors.get_mut(*door_entity) {
+                        door.open = true;
+                    }
+                    blocks_visibility.remove(*door_entity);
+                    blocks_movement.remove(*door_entity);
+                    if let Some(glyph) = renderables.get_mut(*door_entity) {
+                        glyph.glyph = rltk::to_cp437('/');
+                    }
+                    opened_doors.push(Point::new(
+                        path.steps[1] as i32 % map.width,
+                        path.steps[1] as i32 / map.width,
+                    ));
+                } else if path.success && path.steps.len() > 1 {
                     let mut idx = map.xy_idx(pos.x, pos.y);
                     map.blocked[idx] = false;
                     pos.x = path.steps[1] as i32 % map.width;
@@ -66,8 +110,27 @@ impl<'a> System<'a> for MonsterAI {
                     idx = map.xy_idx(pos.x, pos.y);
                     map.blocked[idx] = true;
                     viewshed.dirty = true;
+                    entity_moved
+                        .insert(entity, EntityMoved {})
+                        .expect("Unable to insert marker");
                 }
             }
         }
+
+        // An opened door changes what anyone who can see it can see past it
+        for viewshed in (&mut viewshed).join() {
+            if opened_doors
+                .iter()
+                .any(|door| viewshed.visible_tiles.contains(door))
+            {
+                viewshed.dirty = true;
+            }
+        }
+
+        for (door_entity, idx) in closed_doors.iter() {
+            if doors.get(*door_entity).is_some_and(|door| !door.open) {
+                map.blocked[*idx] = true;
+            }
+        }
     }
 }
@@ -1,16 +1,32 @@
-use crate::{Item, WantsToMelee, WantsToPickUpItem, gamelog::GameLog};
+use crate::{
+    Item, WantsToMelee, WantsToPickUpItem,
+    autopilot::{self, AutoAction},
+    camera,
+    gamelog::GameLog,
+    gui::VendorMode,
+};
 
-use super::{CombatStats, Map, Player, Position, RunState, State, Viewshed};
-use rltk::{Point, Rltk, VirtualKeyCode};
+use super::{
+    BlocksTile, BlocksVisibility, CombatStats, Door, EntityMoved, Hidden, Map, Name, Player,
+    Position, Renderable, RunState, State, TileType, Vendor, Viewshed,
+};
+use rltk::{Point, RGB, RandomNumberGenerator, Rltk, VirtualKeyCode};
 use specs::prelude::*;
-use std::cmp::{max, min};
 
-pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
+/// Moves the player, or attacks, opens or trades with whatever is in the way.
+/// Returns the state to continue in.
+pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> RunState {
     let mut positions = ecs.write_storage::<Position>();
     let mut players = ecs.write_storage::<Player>();
     let mut viewsheds = ecs.write_storage::<Viewshed>();
     let mut wants_to_melee = ecs.write_storage::<WantsToMelee>();
     let combat_stats = ecs.read_storage::<CombatStats>();
+    let mut doors = ecs.write_storage::<Door>();
+    let mut blocks_visibility = ecs.write_storage::<BlocksVisibility>();
+    let mut blocks_movement = ecs.write_storage::<BlocksTile>();
+    let mut renderables = ecs.write_storage::<Renderable>();
+    let mut entity_moved = ecs.write_storage::<EntityMoved>();
+    let vendors = ecs.read_storage::<Vendor>();
     let entities = ecs.entities();
     let map = ecs.fetch::<Map>();
 
@@ -22,11 +38,18 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
             || pos.y + delta_y < 1
             || pos.y + delta_y > map.height - 1
         {
-            return;
+            return RunState::AwaitingInput;
         }
         let destination_idx = map.xy_idx(pos.x + delta_x, pos.y + delta_y);
 
         for potential_target in map.tile_content[destination_idx].iter() {
+            if vendors.get(*potential_target).is_some() {
+                return RunState::ShowVendor {
+                    vendor: *potential_target,
+                    mode: VendorMode::Buy,
+                };
+            }
+
             if let Some(_t) = combat_stats.get(*potential_target) {
                 wants_to_melee
                     .insert(
@@ -36,66 +59,184 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
                         },
                     )
                     .expect("Failed to add melee target");
-                return;
+                return RunState::PlayerTurn;
+            }
+
+            if let Some(door) = doors.get_mut(*potential_target)
+                && !door.open
+            {
+                door.open = true;
+                blocks_visibility.remove(*potential_target);
+                blocks_movement.remove(*potential_target);
+                if let Some(glyph) = renderables.get_mut(*potential_target) {
+                    glyph.glyph = rltk::to_cp437('/');
+                }
+                viewshed.dirty = true;
+                return RunState::PlayerTurn;
             }
         }
 
         if !map.blocked[destination_idx] {
-            pos.x = min(79, max(0, pos.x + delta_x));
-            pos.y = min(49, max(0, pos.y + delta_y));
+            pos.x = (pos.x + delta_x).clamp(0, map.width - 1);
+            pos.y = (pos.y + delta_y).clamp(0, map.height - 1);
 
             let mut ppos = ecs.write_resource::<Point>();
             ppos.x = pos.x;
             ppos.y = pos.y;
 
             viewshed.dirty = true;
+            entity_moved
+                .insert(entity, EntityMoved {})
+                .expect("Unable to insert marker");
         }
     }
+    RunState::PlayerTurn
+}
+
+/// Whether the player is standing on the way down.
+fn on_down_stairs(ecs: &World) -> bool {
+    let player_pos = ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+    map.tiles[map.xy_idx(player_pos.x, player_pos.y)] == TileType::DownStairs
+}
+
+/// Sets the player travelling to the way down, if they've found it.
+fn travel_to_stairs(ecs: &mut World) {
+    match autopilot::known_down_stairs(ecs) {
+        Some(destination) => autopilot::start(ecs, AutoAction::Travel { destination }),
+        None => ecs
+            .fetch_mut::<GameLog>()
+            .add("You haven't found the way down yet."),
+    }
+}
+
+/// The direction a movement key points in, if it is one.
+fn key_direction(key: VirtualKeyCode) -> Option<(i32, i32)> {
+    match key {
+        VirtualKeyCode::Left | VirtualKeyCode::Numpad4 | VirtualKeyCode::H => Some((-1, 0)),
+        VirtualKeyCode::Right | VirtualKeyCode::Numpad6 | VirtualKeyCode::L => Some((1, 0)),
+        VirtualKeyCode::Up | VirtualKeyCode::Numpad8 | VirtualKeyCode::K => Some((0, -1)),
+        VirtualKeyCode::Down | VirtualKeyCode::Numpad2 | VirtualKeyCode::J => Some((0, 1)),
+        VirtualKeyCode::Numpad9 | VirtualKeyCode::Y => Some((1, -1)),
+        VirtualKeyCode::Numpad7 | VirtualKeyCode::U => Some((-1, -1)),
+        VirtualKeyCode::Numpad3 | VirtualKeyCode::N => Some((1, 1)),
+        VirtualKeyCode::Numpad1 | VirtualKeyCode::B => Some((-1, 1)),
+        _ => None,
+    }
 }
+
 pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
-    // Player movement
-    match ctx.key {
-        None => return RunState::AwaitingInput, // Nothing happened
-        Some(key) => match key {
-            VirtualKeyCode::Left | VirtualKeyCode::Numpad4 | VirtualKeyCode::H => {
-                try_move_player(-1, 0, &mut gs.ecs)
-            }
+    // Carry on with whatever the player is doing automatically, until they press a key
+    if autopilot::is_active(&gs.ecs) {
+        if ctx.key.is_some() || ctx.left_click {
+            autopilot::stop(&mut gs.ecs);
+            return RunState::AwaitingInput;
+        }
+        return autopilot::step(&mut gs.ecs);
+    }
 
-            VirtualKeyCode::Right | VirtualKeyCode::Numpad6 | VirtualKeyCode::L => {
-                try_move_player(1, 0, &mut gs.ecs)
-            }
+    if ctx.left_click {
+        travel_to_click(gs, ctx);
+        return RunState::AwaitingInput;
+    }
 
-            VirtualKeyCode::Up | VirtualKeyCode::Numpad8 | VirtualKeyCode::K => {
-                try_move_player(0, -1, &mut gs.ecs)
-            }
+    let Some(key) = ctx.key else {
+        return RunState::AwaitingInput; // Nothing happened
+    };
 
-            VirtualKeyCode::Down | VirtualKeyCode::Numpad2 | VirtualKeyCode::J => {
-                try_move_player(0, 1, &mut gs.ecs)
-            }
+    // Player movement
+    if let Some((delta_x, delta_y)) = key_direction(key) {
+        return try_move_player(delta_x, delta_y, &mut gs.ecs);
+    }
 
-            VirtualKeyCode::Numpad9 | VirtualKeyCode::Y => {
-                try_move_player(1, -1, &mut gs.ecs);
+    match key {
+        VirtualKeyCode::G => get_item(&mut gs.ecs),
+        VirtualKeyCode::I => return RunState::ShowInventory,
+        VirtualKeyCode::M => return RunState::ShowLog { scroll: 0 },
+        VirtualKeyCode::C => return RunState::ShowCharacter,
+        VirtualKeyCode::O => {
+            autopilot::start(&mut gs.ecs, AutoAction::Explore);
+            return RunState::AwaitingInput;
+        }
+        VirtualKeyCode::S => search(&mut gs.ecs),
+        // '>' takes the stairs down, heading for them first if they're elsewhere
+        VirtualKeyCode::Period if ctx.shift => {
+            if on_down_stairs(&gs.ecs) {
+                return RunState::NextLevel;
             }
+            travel_to_stairs(&mut gs.ecs);
+            return RunState::AwaitingInput;
+        }
+        VirtualKeyCode::Space | VirtualKeyCode::Numpad5 | VirtualKeyCode::Period => {} // Wait a turn
+        VirtualKeyCode::Z => {
+            autopilot::start(&mut gs.ecs, AutoAction::Rest);
+            return RunState::AwaitingInput;
+        }
+        VirtualKeyCode::Semicolon | VirtualKeyCode::X => {
+            let cursor = *gs.ecs.fetch::<Point>();
+            return RunState::LookMode { cursor };
+        }
+        VirtualKeyCode::T => {
+            travel_to_stairs(&mut gs.ecs);
+            return RunState::AwaitingInput;
+        }
 
-            VirtualKeyCode::Numpad7 | VirtualKeyCode::U => {
-                try_move_player(-1, -1, &mut gs.ecs);
-            }
+        _ => return RunState::AwaitingInput,
+    }
+    RunState::PlayerTurn
+}
 
-            VirtualKeyCode::Numpad3 | VirtualKeyCode::N => {
-                try_move_player(1, 1, &mut gs.ecs);
-            }
+/// Sets the player travelling to the tile they clicked on, if they've seen it and
+/// it can be walked on.
+fn travel_to_click(gs: &mut State, ctx: &Rltk) {
+    let mouse_pos = ctx.mouse_pos();
+    let Some(target) = camera::screen_to_world(&gs.ecs, Point::new(mouse_pos.0, mouse_pos.1))
+    else {
+        return;
+    };
 
-            VirtualKeyCode::Numpad1 | VirtualKeyCode::B => {
-                try_move_player(-1, 1, &mut gs.ecs);
-            }
+    let destination = {
+        let map = gs.ecs.fetch::<Map>();
+        if target.x < 0 || target.x >= map.width || target.y < 0 || target.y >= map.height {
+            return;
+        }
+        let idx = map.xy_idx(target.x, target.y);
+        if !map.revealed_tiles[idx] || !map.tiles[idx].is_walkable() {
+            return;
+        }
+        idx
+    };
+    autopilot::start(&mut gs.ecs, AutoAction::Travel { destination });
+}
 
-            VirtualKeyCode::G => get_item(&mut gs.ecs),
-            VirtualKeyCode::I => return RunState::ShowInventory,
+/// Moves the look cursor around the part of the map on screen. Looking around
+/// doesn't take a turn.
+pub fn look_input(gs: &mut State, ctx: &mut Rltk, cursor: Point) -> RunState {
+    let Some(key) = ctx.key else {
+        return RunState::LookMode { cursor };
+    };
 
-            _ => return RunState::AwaitingInput,
-        },
+    match key {
+        VirtualKeyCode::Escape | VirtualKeyCode::Semicolon | VirtualKeyCode::X => {
+            RunState::AwaitingInput
+        }
+        _ => {
+            let Some((delta_x, delta_y)) = key_direction(key) else {
+                return RunState::LookMode { cursor };
+            };
+            let origin = camera::get_screen_bounds(&gs.ecs);
+            let map = gs.ecs.fetch::<Map>();
+            let x = (cursor.x + delta_x)
+                .clamp(origin.x, origin.x + camera::VIEW_WIDTH - 1)
+                .clamp(0, map.width - 1);
+            let y = (cursor.y + delta_y)
+                .clamp(origin.y, origin.y + camera::VIEW_HEIGHT - 1)
+                .clamp(0, map.height - 1);
+            RunState::LookMode {
+                cursor: Point::new(x, y),
+            }
+        }
     }
-    RunState::PlayerTurn
 }
 
 fn get_item(ecs: &mut World) {
@@ -114,9 +255,7 @@ fn get_item(ecs: &mut World) {
     }
 
     match target_item {
-        None => gamelog
-            .entries
-            .push(String::from("There is nothing here to pick up.")),
+        None => gamelog.add("There is nothing here to pick up."),
         Some(item) => {
             let mut pickup = ecs.write_storage::<WantsToPickUpItem>();
             pickup
@@ -124,10 +263,54 @@ fn get_item(ecs: &mut World) {
                     *player_entity,
                     WantsToPickUpItem {
                         collected_by: *player_entity,
-                        item: item,
+                        item,
                     },
                 )
                 .expect("Unable to insert want to pickup.");
         }
     }
 }
+
+/// Spend a turn looking around for hidden things near the player.
+fn search(ecs: &mut World) {
+    const SEARCH_RADIUS: f32 = 3.0;
+
+    let player_pos = ecs.fetch::<Point>();
+    let player_entity = ecs.fetch::<Entity>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+    let positions = ecs.read_storage::<Position>();
+    let names = ecs.read_storage::<Name>();
+    let entities = ecs.entities();
+    let mut hidden = ecs.write_storage::<Hidden>();
+    let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+    let Some(viewshed) = viewsheds.get(*player_entity) else {
+        return;
+    };
+
+    let mut found: Vec<Entity> = Vec::new();
+    for (entity, pos, _hidden) in (&entities, &positions, &hidden).join() {
+        let point = Point::new(pos.x, pos.y);
+        let distance = rltk::DistanceAlg::Pythagoras.distance2d(point, *player_pos);
+        if distance <= SEARCH_RADIUS
+            && viewshed.visible_tiles.contains(&point)
+            && rng.roll_dice(1, 6) > 2
+        {
+            found.push(entity);
+        }
+    }
+
+    if found.is_empty() {
+        gamelog.add("You search the area but find nothing.");
+    }
+    for entity in found {
+        hidden.remove(entity);
+        if let Some(name) = names.get(entity) {
+            gamelog.add_colored(
+                format!("You spot a {}!", &name.name),
+                RGB::named(rltk::ORANGE),
+            );
+        }
+    }
+}
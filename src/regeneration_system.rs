@@ -0,0 +1,107 @@
+use super::{CombatStats, HungerClock, HungerState, TurnCounter};
+use specs::prelude::*;
+
+/// How often things naturally recover some health, in turns.
+const REGENERATION_TURNS: i32 = 10;
+
+/// Each recovery heals this fraction of an entity's maximum health, and at least
+/// one hit point, so tougher things heal faster.
+const REGENERATION_DIVISOR: i32 = 20;
+
+/// Slowly heals anything that has been hurt, as long as it isn't going hungry.
+pub struct RegenerationSystem {}
+
+/// Whether something with this hunger clock is well enough fed to heal on its own.
+/// Anything that doesn't get hungry always is.
+pub fn can_regenerate(clock: Option<&HungerClock>) -> bool {
+    clock.is_none_or(|clock| {
+        clock.state == HungerState::WellFed || clock.state == HungerState::Normal
+    })
+}
+
+/// How much health something with these stats gets back each time it recovers.
+fn regeneration_amount(stats: &CombatStats) -> i32 {
+    i32::max(1, stats.max_hp / REGENERATION_DIVISOR)
+}
+
+impl<'a> System<'a> for RegenerationSystem {
+    type SystemData = (
+        ReadExpect<'a, TurnCounter>,
+        WriteStorage<'a, CombatStats>,
+        ReadStorage<'a, HungerClock>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (turns, mut combat_stats, hunger_clocks) = data;
+
+        if turns.turn % REGENERATION_TURNS != 0 {
+            return;
+        }
+
+        for (stats, clock) in (&mut combat_stats, hunger_clocks.maybe()).join() {
+            if stats.hp > 0 && can_regenerate(clock) {
+                stats.hp = i32::min(stats.hp + regeneration_amount(stats), stats.max_hp);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(hp: i32, max_hp: i32) -> CombatStats {
+        CombatStats {
+            max_hp,
+            hp,
+            defense: 0,
+            power: 0,
+        }
+    }
+
+    /// Runs the system on something with `hp` out of `max_hp` on turn `turn`, and
+    /// returns its health afterwards.
+    fn regenerate(hp: i32, max_hp: i32, clock: Option<HungerClock>, turn: i32) -> i32 {
+        let mut ecs = World::new();
+        ecs.register::<CombatStats>();
+        ecs.register::<HungerClock>();
+        ecs.insert(TurnCounter { turn });
+        let mut builder = ecs.create_entity().with(stats(hp, max_hp));
+        if let Some(clock) = clock {
+            builder = builder.with(clock);
+        }
+        let entity = builder.build();
+
+        RegenerationSystem {}.run_now(&ecs);
+        ecs.read_storage::<CombatStats>().get(entity).unwrap().hp
+    }
+
+    #[test]
+    fn tougher_things_heal_faster() {
+        assert_eq!(regeneration_amount(&stats(1, 10)), 1);
+        assert_eq!(regeneration_amount(&stats(1, 30)), 1);
+        assert_eq!(regeneration_amount(&stats(1, 100)), 5);
+        assert_eq!(regeneration_amount(&stats(1, 400)), 20);
+    }
+
+    #[test]
+    fn healing_only_happens_every_few_turns() {
+        assert_eq!(regenerate(10, 100, None, REGENERATION_TURNS), 15);
+        assert_eq!(regenerate(10, 100, None, REGENERATION_TURNS + 1), 10);
+    }
+
+    #[test]
+    fn healing_stops_at_max_hp() {
+        assert_eq!(regenerate(98, 100, None, REGENERATION_TURNS), 100);
+    }
+
+    #[test]
+    fn the_hungry_and_the_dead_do_not_heal() {
+        let hungry = HungerClock {
+            state: HungerState::Hungry,
+            duration: 10,
+        };
+        assert_eq!(regenerate(10, 100, Some(hungry), REGENERATION_TURNS), 10);
+        assert_eq!(regenerate(0, 100, None, REGENERATION_TURNS), 0);
+    }
+}
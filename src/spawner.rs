@@ -1,63 +1,94 @@
-use std::usize;
-
 use super::{
-    CombatStats, HealingPotion, Item, MAPWIDTH, Monster, Name, Player, Position, Rect, Renderable,
-    Viewshed,
+    BlocksTile, BlocksVisibility, CombatStats, Corpse, Description, DetectsMonsters, Door,
+    EntryTrigger, GoldPile, HealingPotion, Hidden, InBackpack, InflictsDamage, Item, ItemValue,
+    MagicMapper, Map, Monster, Name, Player, Position, ProvidesFood, Rect, Renderable,
+    SingleActivation, TeleportsTarget, Vendor, Viewshed, Wallet, hunger_system,
 };
 use rltk::{RGB, RandomNumberGenerator};
 use specs::prelude::*;
 
 const MAX_MONSTERS_PER_ROOM: i32 = 4;
 const MAX_ITEMS_PER_ROOM: i32 = 2;
+const MAX_TRAPS_PER_ROOM: i32 = 2;
+
+/// Spawns an item at a given location, like `health_potion`.
+type ItemSpawner = fn(&mut World, i32, i32) -> Entity;
+
+/// What each monster might be carrying: each item has a 1 in N chance of being
+/// in their backpack.
+const ORC_LOOT: &[(i32, ItemSpawner)] = &[(3, health_potion)];
+const GOBLIN_LOOT: &[(i32, ItemSpawner)] = &[(4, health_potion), (4, rations)];
 
-/// Spawn a room with monsters and items.
+/// Spawn a room with monsters, items and traps.
 pub fn spawn_room(ecs: &mut World, room: &Rect) {
-    let mut monster_spawner_points: Vec<usize> = Vec::new();
-    let mut item_spawn_points: Vec<usize> = Vec::new();
+    let mut possible_targets: Vec<usize> = Vec::new();
+    {
+        let map = ecs.fetch::<Map>();
+        for y in room.y1 + 1..=room.y2 {
+            for x in room.x1 + 1..=room.x2 {
+                possible_targets.push(map.xy_idx(x, y));
+            }
+        }
+    }
+
+    spawn_region(ecs, &possible_targets);
+}
 
+/// Spawn monsters, items and traps on a set of map indices.
+pub fn spawn_region(ecs: &mut World, area: &[usize]) {
+    let monster_spawner_points: Vec<usize>;
+    let item_spawn_points: Vec<usize>;
+    let trap_spawn_points: Vec<usize>;
+
+    let map_width = ecs.fetch::<Map>().width as usize;
     {
         let mut rng = ecs.write_resource::<RandomNumberGenerator>();
         let num_monsters = rng.roll_dice(1, MAX_MONSTERS_PER_ROOM + 2) - 3;
         let num_items = rng.roll_dice(1, MAX_ITEMS_PER_ROOM + 2) - 3;
+        let num_traps = rng.roll_dice(1, MAX_TRAPS_PER_ROOM + 2) - 3;
 
-        for _i in 0..num_monsters {
-            let mut added = false;
-            while !added {
-                let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
-                let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
-                let idx = (y * MAPWIDTH) + x;
-                if !monster_spawner_points.contains(&idx) {
-                    monster_spawner_points.push(idx);
-                    added = true;
-                }
-            }
-        }
-
-        for _i in 0..num_items {
-            let mut added = false;
-            while !added {
-                let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
-                let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
-                let idx = (y * MAPWIDTH) + x;
-                if !item_spawn_points.contains(&idx) {
-                    item_spawn_points.push(idx);
-                    added = true;
-                }
-            }
-        }
+        let mut areas: Vec<usize> = Vec::from(area);
+        monster_spawner_points = take_random_points(&mut rng, &mut areas, num_monsters);
+        item_spawn_points = take_random_points(&mut rng, &mut areas, num_items);
+        trap_spawn_points = take_random_points(&mut rng, &mut areas, num_traps);
     }
 
     for idx in monster_spawner_points.iter() {
-        let x = *idx % MAPWIDTH;
-        let y = *idx / MAPWIDTH;
+        let x = *idx % map_width;
+        let y = *idx / map_width;
         random_monster(ecs, x as i32, y as i32);
     }
 
     for idx in item_spawn_points.iter() {
-        let x = *idx % MAPWIDTH;
-        let y = idx / MAPWIDTH;
-        health_potion(ecs, x as i32, y as i32);
+        let x = *idx % map_width;
+        let y = idx / map_width;
+        random_item(ecs, x as i32, y as i32);
+    }
+
+    for idx in trap_spawn_points.iter() {
+        let x = *idx % map_width;
+        let y = idx / map_width;
+        random_trap(ecs, x as i32, y as i32);
+    }
+}
+
+/// Remove up to `count` random map indices from `areas` and return them.
+fn take_random_points(
+    rng: &mut RandomNumberGenerator,
+    areas: &mut Vec<usize>,
+    count: i32,
+) -> Vec<usize> {
+    let mut points: Vec<usize> = Vec::new();
+
+    for _i in 0..count {
+        if areas.is_empty() {
+            break;
+        }
+        let array_index = (rng.roll_dice(1, areas.len() as i32) - 1) as usize;
+        points.push(areas.remove(array_index));
     }
+
+    points
 }
 
 /// Spawn the player and return their entity object.
@@ -71,6 +102,7 @@ pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
             glyph: rltk::to_cp437('@'),
             fg: RGB::named(rltk::YELLOW),
             bg: RGB::named(rltk::BLACK),
+            render_order: 0,
         })
         .with(Player {})
         .with(Viewshed {
@@ -81,12 +113,17 @@ pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
         .with(Name {
             name: String::from("Player"),
         })
+        .with(Description {
+            text: String::from("You, an adventurer in search of fortune."),
+        })
         .with(CombatStats {
             max_hp: 30,
             hp: 30,
             defense: 2,
             power: 5,
         })
+        .with(Wallet { gold: 50 })
+        .with(hunger_system::well_fed())
         .build()
 }
 
@@ -103,12 +140,51 @@ pub fn random_monster(ecs: &mut World, x: i32, y: i32) -> Entity {
     }
 }
 
-fn orc(ecs: &mut World, x: i32, y: i32) -> Entity {
-    monster(ecs, x, y, rltk::to_cp437('o'), "Orc")
+pub fn orc(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let gold = ecs
+        .write_resource::<RandomNumberGenerator>()
+        .roll_dice(2, 6);
+    let orc = monster(
+        ecs,
+        x,
+        y,
+        rltk::to_cp437('o'),
+        "Orc",
+        "A hulking brute that hits hard.",
+        gold,
+    );
+    roll_loot(ecs, orc, ORC_LOOT);
+    orc
 }
 
-fn goblin(ecs: &mut World, x: i32, y: i32) -> Entity {
-    monster(ecs, x, y, rltk::to_cp437('g'), "Goblin")
+pub fn goblin(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let gold = ecs
+        .write_resource::<RandomNumberGenerator>()
+        .roll_dice(1, 6);
+    let goblin = monster(
+        ecs,
+        x,
+        y,
+        rltk::to_cp437('g'),
+        "Goblin",
+        "A small, sly creature with a taste for gold.",
+        gold,
+    );
+    roll_loot(ecs, goblin, GOBLIN_LOOT);
+    goblin
+}
+
+/// Rolls on a loot table, putting whatever comes up in `owner`'s backpack.
+fn roll_loot(ecs: &mut World, owner: Entity, loot_table: &[(i32, ItemSpawner)]) {
+    for (chance, spawn_item) in loot_table.iter() {
+        let roll = ecs
+            .write_resource::<RandomNumberGenerator>()
+            .roll_dice(1, *chance);
+        if roll == 1 {
+            let item = spawn_item(ecs, 0, 0);
+            give_item(ecs, owner, item);
+        }
+    }
 }
 
 fn monster<S: ToString>(
@@ -117,6 +193,8 @@ fn monster<S: ToString>(
     y: i32,
     glyph: rltk::FontCharType,
     name: S,
+    description: &str,
+    gold: i32,
 ) -> Entity {
     ecs.create_entity()
         .with(Position { x, y })
@@ -124,6 +202,7 @@ fn monster<S: ToString>(
             glyph,
             fg: RGB::named(rltk::RED),
             bg: RGB::named(rltk::BLACK),
+            render_order: 1,
         })
         .with(Viewshed {
             visible_tiles: Vec::new(),
@@ -134,27 +213,295 @@ fn monster<S: ToString>(
         .with(Name {
             name: name.to_string(),
         })
+        .with(Description {
+            text: description.to_string(),
+        })
         .with(CombatStats {
             max_hp: 16,
             hp: 16,
             defense: 1,
             power: 4,
         })
+        .with(Wallet { gold })
         .build()
 }
 
-fn health_potion(ecs: &mut World, x: i32, y: i32) -> Entity {
+/// Spawn a random item at a given location.
+pub fn random_item(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let roll: i32;
+    {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        roll = rng.roll_dice(1, 8);
+    }
+    match roll {
+        1 | 2 => rations(ecs, x, y),
+        3 => magic_mapping_scroll(ecs, x, y),
+        4 => detect_monsters_scroll(ecs, x, y),
+        _ => health_potion(ecs, x, y),
+    }
+}
+
+pub fn rations(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('%'),
+            fg: RGB::named(rltk::GREEN),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: String::from("Rations"),
+        })
+        .with(Description {
+            text: String::from("Dried meat and hard bread. Eating them staves off hunger."),
+        })
+        .with(Item {})
+        .with(ProvidesFood {})
+        .with(ItemValue { value: 10 })
+        .build()
+}
+
+pub fn health_potion(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph: rltk::to_cp437('¡'),
             fg: RGB::named(rltk::MAGENTA),
             bg: RGB::named(rltk::BLACK),
+            render_order: 2,
         })
         .with(Name {
             name: String::from("Potion of Healing"),
         })
+        .with(Description {
+            text: String::from("A potion that heals wounds when drunk."),
+        })
         .with(Item {})
         .with(HealingPotion { heal_amount: 8 })
+        .with(ItemValue { value: 20 })
+        .build()
+}
+
+/// Spawn a scroll of magic mapping at a given location.
+pub fn magic_mapping_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437(')'),
+            fg: RGB::named(rltk::CYAN3),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: String::from("Scroll of Magic Mapping"),
+        })
+        .with(Description {
+            text: String::from("Reading it reveals the layout of the whole level."),
+        })
+        .with(Item {})
+        .with(MagicMapper {})
+        .with(ItemValue { value: 50 })
+        .build()
+}
+
+/// Spawn a scroll of detect monsters at a given location.
+pub fn detect_monsters_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437(')'),
+            fg: RGB::named(rltk::ORANGE),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: String::from("Scroll of Detect Monsters"),
+        })
+        .with(Description {
+            text: String::from("Reading it lets you sense every monster on the level for a while."),
+        })
+        .with(Item {})
+        .with(DetectsMonsters { turns: 50 })
+        .with(ItemValue { value: 40 })
+        .build()
+}
+
+/// Spawn a pile of gold coins at a given location.
+pub fn gold_pile(ecs: &mut World, x: i32, y: i32, amount: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('$'),
+            fg: RGB::named(rltk::GOLD),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: format!("{} gold", amount),
+        })
+        .with(Description {
+            text: format!("A pile of {} gold coins.", amount),
+        })
+        .with(Item {})
+        .with(ItemValue { value: amount })
+        .with(GoldPile { amount })
+        .build()
+}
+
+/// Spawn the remains of `name` where it died. They can be picked up and eaten
+/// until they rot away. Corpses have no `ItemValue`: nobody will buy one, so they
+/// never show up at a vendor.
+pub fn corpse(ecs: &mut World, x: i32, y: i32, name: &str) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('%'),
+            fg: RGB::named(rltk::DARK_RED),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 3,
+        })
+        .with(Name {
+            name: format!("{} corpse", name),
+        })
+        .with(Description {
+            text: format!(
+                "The remains of a slain {}. Still fresh enough to eat, for now.",
+                name.to_lowercase()
+            ),
+        })
+        .with(Item {})
+        .with(ProvidesFood {})
+        .with(Corpse { turns_left: 200 })
+        .build()
+}
+
+/// Put an item straight into `owner`'s backpack rather than on the map.
+pub fn give_item(ecs: &mut World, owner: Entity, item: Entity) {
+    ecs.write_storage::<Position>().remove(item);
+    ecs.write_storage::<InBackpack>()
+        .insert(item, InBackpack { owner })
+        .expect("Unable to insert backpack entry.");
+}
+
+/// Spawn a shopkeeper at a given location.
+pub fn vendor(ecs: &mut World, x: i32, y: i32, name: &str) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('☺'),
+            fg: RGB::named(rltk::GOLD),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 1,
+        })
+        .with(Name {
+            name: String::from(name),
+        })
+        .with(Description {
+            text: String::from("A shopkeeper. Walk into them to trade."),
+        })
+        .with(BlocksTile {})
+        .with(Vendor {})
+        .build()
+}
+
+/// Spawn a closed door at a given location.
+pub fn door(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('+'),
+            fg: RGB::named(rltk::CHOCOLATE),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 4,
+        })
+        .with(Name {
+            name: String::from("Door"),
+        })
+        .with(Description {
+            text: String::from("A wooden door."),
+        })
+        .with(BlocksTile {})
+        .with(BlocksVisibility {})
+        .with(Door { open: false })
+        .build()
+}
+
+/// Spawn a random hidden trap at a given location.
+pub fn random_trap(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let roll: i32;
+    {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        roll = rng.roll_dice(1, 3);
+    }
+    match roll {
+        1 => bear_trap(ecs, x, y),
+        2 => dart_trap(ecs, x, y),
+        _ => teleport_trap(ecs, x, y),
+    }
+}
+
+fn bear_trap(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('^'),
+            fg: RGB::named(rltk::RED),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 4,
+        })
+        .with(Name {
+            name: String::from("Bear Trap"),
+        })
+        .with(Description {
+            text: String::from("A set of iron jaws, waiting for a careless foot."),
+        })
+        .with(Hidden {})
+        .with(EntryTrigger {})
+        .with(InflictsDamage { damage: 6 })
+        .with(SingleActivation {})
+        .build()
+}
+
+fn dart_trap(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('^'),
+            fg: RGB::named(rltk::ORANGE),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 4,
+        })
+        .with(Name {
+            name: String::from("Dart Trap"),
+        })
+        .with(Description {
+            text: String::from("A pressure plate that fires a dart each time it is stepped on."),
+        })
+        .with(Hidden {})
+        .with(EntryTrigger {})
+        .with(InflictsDamage { damage: 3 })
+        .build()
+}
+
+fn teleport_trap(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('^'),
+            fg: RGB::named(rltk::MAGENTA),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 4,
+        })
+        .with(Name {
+            name: String::from("Teleport Trap"),
+        })
+        .with(Description {
+            text: String::from("A glowing rune that whisks whoever steps on it elsewhere."),
+        })
+        .with(Hidden {})
+        .with(EntryTrigger {})
+        .with(TeleportsTarget {})
+        .with(SingleActivation {})
         .build()
 }
@@ -0,0 +1,84 @@
+use super::{
+    InBackpack, ItemValue, Name, Wallet,
+    gamelog::{GameLog, LogFragment},
+};
+use rltk::RGB;
+use specs::prelude::*;
+
+/// What a vendor charges for an item.
+pub fn buy_price(value: &ItemValue) -> i32 {
+    value.value
+}
+
+/// What a vendor pays for an item: half what they'd sell it for.
+pub fn sell_price(value: &ItemValue) -> i32 {
+    value.value / 2
+}
+
+/// The player buys `item` out of a vendor's stock, if they can afford it.
+pub fn buy_item(ecs: &mut World, item: Entity) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let values = ecs.read_storage::<ItemValue>();
+    let names = ecs.read_storage::<Name>();
+    let mut wallets = ecs.write_storage::<Wallet>();
+    let mut backpack = ecs.write_storage::<InBackpack>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+    let (Some(value), Some(name), Some(wallet)) = (
+        values.get(item),
+        names.get(item),
+        wallets.get_mut(player_entity),
+    ) else {
+        return;
+    };
+
+    let price = buy_price(value);
+    if wallet.gold < price {
+        gamelog.add(format!("You can't afford the {}.", name.name));
+        return;
+    }
+
+    wallet.gold -= price;
+    backpack
+        .insert(
+            item,
+            InBackpack {
+                owner: player_entity,
+            },
+        )
+        .expect("Unable to insert backpack entry.");
+    gamelog.add_fragments(vec![
+        LogFragment::plain(format!("You buy the {} for ", name.name)),
+        LogFragment::new(format!("{} gold", price), RGB::named(rltk::GOLD)),
+        LogFragment::plain("."),
+    ]);
+}
+
+/// The player sells `item` from their backpack to `vendor`.
+pub fn sell_item(ecs: &mut World, vendor: Entity, item: Entity) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let values = ecs.read_storage::<ItemValue>();
+    let names = ecs.read_storage::<Name>();
+    let mut wallets = ecs.write_storage::<Wallet>();
+    let mut backpack = ecs.write_storage::<InBackpack>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+    let (Some(value), Some(name), Some(wallet)) = (
+        values.get(item),
+        names.get(item),
+        wallets.get_mut(player_entity),
+    ) else {
+        return;
+    };
+
+    let price = sell_price(value);
+    wallet.gold += price;
+    backpack
+        .insert(item, InBackpack { owner: vendor })
+        .expect("Unable to insert backpack entry.");
+    gamelog.add_fragments(vec![
+        LogFragment::plain(format!("You sell the {} for ", name.name)),
+        LogFragment::new(format!("{} gold", price), RGB::named(rltk::GOLD)),
+        LogFragment::plain("."),
+    ]);
+}
@@ -0,0 +1,127 @@
+use super::{
+    EntityMoved, EntryTrigger, Hidden, InflictsDamage, Map, Name, Position, SingleActivation,
+    SufferDamage, TeleportsTarget, TileType, Viewshed, gamelog::GameLog,
+};
+use rltk::{Point, RGB, RandomNumberGenerator};
+use specs::prelude::*;
+
+pub struct TriggerSystem {}
+
+impl<'a> System<'a> for TriggerSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Map>,
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, Point>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteStorage<'a, EntityMoved>,
+        WriteStorage<'a, Position>,
+        ReadStorage<'a, EntryTrigger>,
+        WriteStorage<'a, Hidden>,
+        ReadStorage<'a, Name>,
+        Entities<'a>,
+        ReadStorage<'a, InflictsDamage>,
+        WriteStorage<'a, SufferDamage>,
+        ReadStorage<'a, SingleActivation>,
+        ReadStorage<'a, TeleportsTarget>,
+        WriteStorage<'a, Viewshed>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            map,
+            player_entity,
+            mut player_pos,
+            mut log,
+            mut rng,
+            mut entity_moved,
+            mut positions,
+            entry_trigger,
+            mut hidden,
+            names,
+            entities,
+            inflicts_damage,
+            mut inflict_damage,
+            single_activation,
+            teleports,
+            mut viewsheds,
+        ) = data;
+
+        let mut remove_entities: Vec<Entity> = Vec::new();
+        let mut teleported: Vec<Entity> = Vec::new();
+
+        // Only traps the player can see going off are worth mentioning
+        let player_sees = |point: Point| {
+            viewsheds
+                .get(*player_entity)
+                .is_some_and(|viewshed| viewshed.visible_tiles.contains(&point))
+        };
+
+        for (entity, _moved, pos) in (&entities, &entity_moved, &positions).join() {
+            let idx = map.xy_idx(pos.x, pos.y);
+            for entity_id in map.tile_content[idx].iter() {
+                // Don't let an entity trigger itself
+                if entity == *entity_id || entry_trigger.get(*entity_id).is_none() {
+                    continue;
+                }
+
+                if let Some(name) = names.get(*entity_id)
+                    && player_sees(Point::new(pos.x, pos.y))
+                {
+                    log.add_colored(
+                        format!("{} triggers!", &name.name),
+                        RGB::named(rltk::ORANGE),
+                    );
+                }
+
+                // The trap is no longer a secret once it's been sprung
+                hidden.remove(*entity_id);
+
+                if let Some(damage) = inflicts_damage.get(*entity_id) {
+                    SufferDamage::new_damage(&mut inflict_damage, entity, damage.damage);
+                }
+
+                if teleports.get(*entity_id).is_some() {
+                    teleported.push(entity);
+                }
+
+                if single_activation.get(*entity_id).is_some() {
+                    remove_entities.push(*entity_id);
+                }
+            }
+        }
+
+        // Teleporters drop their victims on a random open floor tile
+        let open_floor: Vec<usize> = (0..map.tiles.len())
+            .filter(|idx| map.tiles[*idx] == TileType::Floor && !map.blocked[*idx])
+            .collect();
+
+        for entity in teleported {
+            if open_floor.is_empty() {
+                break;
+            }
+            let roll = rng.roll_dice(1, open_floor.len() as i32) - 1;
+            let idx = open_floor[roll as usize];
+            let destination = (idx as i32 % map.width, idx as i32 / map.width);
+
+            if let Some(pos) = positions.get_mut(entity) {
+                pos.x = destination.0;
+                pos.y = destination.1;
+            }
+            if let Some(viewshed) = viewsheds.get_mut(entity) {
+                viewshed.dirty = true;
+            }
+            if entity == *player_entity {
+                player_pos.x = destination.0;
+                player_pos.y = destination.1;
+            }
+        }
+
+        for trap in remove_entities.iter() {
+            entities.delete(*trap).expect("Unable to delete trap");
+        }
+
+        entity_moved.clear();
+    }
+}